@@ -58,3 +58,6 @@ extern "C" {}
 use num_derive::*;
 
 include!("metis.rs");
+
+/// Size of the `options` array, `METIS_NOPTIONS` in metis.h
+pub const METIS_NOPTIONS: usize = 40;
@@ -0,0 +1,58 @@
+//! Compare the byte-level parsers with the line-based parser using `Line::parse`
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use metis::{graph::CSRGraph, io::graph::*};
+
+/// `n x n` grid graph with edge weights in METIS graph format
+fn grid(n: usize) -> String {
+    let vertex = |i: usize, j: usize| i * n + j + 1;
+    let mut lines = vec![format!("{} {} 001", n * n, 2 * n * (n - 1))];
+    for i in 0..n {
+        for j in 0..n {
+            let mut line = Vec::new();
+            let mut push = |v: usize| line.push(format!("{} {}", v, (i + j) % 7 + 1));
+            if i > 0 {
+                push(vertex(i - 1, j));
+            }
+            if j > 0 {
+                push(vertex(i, j - 1));
+            }
+            if j + 1 < n {
+                push(vertex(i, j + 1));
+            }
+            if i + 1 < n {
+                push(vertex(i + 1, j));
+            }
+            lines.push(line.join(" "));
+        }
+    }
+    lines.join("\n")
+}
+
+fn parse(c: &mut Criterion) {
+    let four_elt = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../metis-src/metis-5.1.0/graphs/4elt.graph"
+    ))
+    .unwrap();
+    let inputs = [("4elt", four_elt), ("grid300", grid(300))];
+
+    let mut group = c.benchmark_group("parse");
+    for (name, input) in &inputs {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("lines", name), input, |b, input| {
+            b.iter(|| CSRGraph::from_metis_graph_str(input).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("bytes", name), input, |b, input| {
+            b.iter(|| CSRGraph::from_metis_graph_bytes(input.as_bytes()).unwrap())
+        });
+        #[cfg(feature = "rayon")]
+        group.bench_with_input(BenchmarkId::new("parallel", name), input, |b, input| {
+            b.iter(|| CSRGraph::from_metis_graph_bytes_parallel(input.as_bytes()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
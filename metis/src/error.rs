@@ -3,7 +3,16 @@
 use metis_sys::rstatus_et;
 use num_traits::FromPrimitive;
 
-use crate::io::graph::GraphFileError;
+use crate::{
+    graph::WeightConversionError,
+    io::{
+        graph::GraphFileError, mesh::MeshFileError, partition::PartitionWeightsFileError,
+        vector::VectorFileError,
+    },
+    metrics::PartitionError,
+    options::OptionError,
+    ordering::PermutationError,
+};
 
 const ISSUE_URL: &str = "https://github.com/termoshtt/metis/issues";
 
@@ -15,8 +24,78 @@ pub enum Error {
     #[error("METIS routine ({api_name}) cannot allocate required memory")]
     MemoryCannotAllocate { api_name: String },
 
+    #[error("Invalid input for METIS routine ({api_name}): {reason}")]
+    InvalidInput { api_name: String, reason: String },
+
+    #[error(
+        "METIS routine ({api_name}) failed with error code {code}. Please send a bug report to {}",
+        ISSUE_URL
+    )]
+    Internal { api_name: String, code: i32 },
+
+    #[error(
+        "METIS routine ({api_name}) returned an invalid output: {reason}. Please send a bug report to {}",
+        ISSUE_URL
+    )]
+    InvalidOutput { api_name: String, reason: String },
+
     #[error(transparent)]
     InvalidGraphFile(#[from] GraphFileError),
+
+    #[error(transparent)]
+    InvalidMeshFile(#[from] MeshFileError),
+
+    #[error(transparent)]
+    InvalidPartitionWeightsFile(#[from] PartitionWeightsFileError),
+
+    #[error(transparent)]
+    InvalidVectorFile(#[from] VectorFileError),
+
+    #[error(transparent)]
+    InvalidWeight(#[from] WeightConversionError),
+
+    #[error(transparent)]
+    InvalidOption(#[from] OptionError),
+
+    #[error(transparent)]
+    InvalidPermutation(#[from] PermutationError),
+
+    #[error(transparent)]
+    InvalidPartition(#[from] PartitionError),
+}
+
+impl Error {
+    pub(crate) fn invalid_input(api_name: &str, reason: impl Into<String>) -> Self {
+        Error::InvalidInput {
+            api_name: api_name.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Check the length of an input array
+pub(crate) fn check_len(api_name: &str, name: &str, actual: usize, expected: usize) -> Result<()> {
+    if actual != expected {
+        return Err(Error::invalid_input(
+            api_name,
+            format!(
+                "{} must have {} elements, but has {}",
+                name, expected, actual
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Check all weights are non-negative
+pub(crate) fn check_non_negative(api_name: &str, name: &str, values: &[i32]) -> Result<()> {
+    if let Some((i, w)) = values.iter().enumerate().find(|(_, &w)| w < 0) {
+        return Err(Error::invalid_input(
+            api_name,
+            format!("{}[{}] must be non-negative, but is {}", name, i, w),
+        ));
+    }
+    Ok(())
 }
 
 pub(crate) trait MetisErrorCodeCheck {
@@ -25,29 +104,45 @@ pub(crate) trait MetisErrorCodeCheck {
 
 impl MetisErrorCodeCheck for i32 {
     fn check(self, api_name: &str) -> Result<()> {
-        let st = rstatus_et::from_i32(self).unwrap_or_else(|| {
-            panic!(
-                "Invalid return value ({}) of METIS routine ({}). This should be a bug of METIS. Please send a bug report to {}",
-                self, api_name, ISSUE_URL
-            )
-        });
+        let internal = || Error::Internal {
+            api_name: api_name.into(),
+            code: self,
+        };
+        let st = rstatus_et::from_i32(self).ok_or_else(internal)?;
         match st {
             rstatus_et::METIS_OK => Ok(()),
             rstatus_et::METIS_ERROR_MEMORY => Err(Error::MemoryCannotAllocate {
                 api_name: api_name.into(),
             }),
-
-            // Following two cases must be bug of this crate, and cannot be recoverted by user.
-            rstatus_et::METIS_ERROR_INPUT => panic!(
-                "METIS routine ({}) raises METIS_ERROR_INPUT, which must be handled by metis crate. Please send a bug report to {}",
+            // Inputs are validated by the wrappers as possible,
+            // but METIS may find other problems, e.g. too large number of parts
+            rstatus_et::METIS_ERROR_INPUT => Err(Error::invalid_input(
                 api_name,
-                ISSUE_URL,
-            ),
-            rstatus_et::METIS_ERROR => panic!(
-                "METIS routine ({}) raises unknown error. Please send a bug report to {}",
-                api_name,
-                ISSUE_URL,
-            ),
+                "METIS rejected the input. See the output of METIS for details",
+            )),
+            rstatus_et::METIS_ERROR => Err(internal()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check() {
+        assert!((rstatus_et::METIS_OK as i32).check("METIS_Test").is_ok());
+        assert!(matches!(
+            (rstatus_et::METIS_ERROR_INPUT as i32).check("METIS_Test"),
+            Err(Error::InvalidInput { .. })
+        ));
+        assert!(matches!(
+            (rstatus_et::METIS_ERROR as i32).check("METIS_Test"),
+            Err(Error::Internal { code: -4, .. })
+        ));
+        assert!(matches!(
+            12345.check("METIS_Test"),
+            Err(Error::Internal { code: 12345, .. })
+        ));
+    }
+}
@@ -1,100 +1,805 @@
 //! Graph structures
 
-use crate::io::graph::*;
+use std::io::{self, Write};
+#[cfg(feature = "mmap")]
+use std::{fs, path::Path};
+
+use crate::{
+    error,
+    io::{compress, graph::*},
+    options::Numbering,
+};
 
 /// uncompressed graph
-#[derive(Debug, Clone)]
+///
+/// Vertex indices are 1-based as in METIS graph file.
+#[derive(Debug, Clone, PartialEq)]
 pub struct UndirectedGraph {
     vertex_size: usize,
     edges: Vec<(i32, i32)>,
 }
 
 impl FromMetisGraphFormat for UndirectedGraph {
-    fn from_metis_graph_iter(
-        header: &Header,
-        lines: impl Iterator<Item = Result<Line, LineError>>,
-    ) -> Result<Self, GraphFileError> {
-        let mut edges = Vec::with_capacity(header.num_edges);
-        for line in lines {
-            let line = line?;
-            let from_index = line.from_index;
-            for to_index in line.vertices {
-                if from_index < to_index {
-                    edges.push((from_index, to_index));
-                }
-            }
+    type Visitor = UndirectedGraphVisitor;
+}
+
+/// [GraphVisitor] constructing [UndirectedGraph]
+#[derive(Debug, Default)]
+pub struct UndirectedGraphVisitor {
+    header: Option<Header>,
+    edges: Vec<(i32, i32)>,
+}
+
+impl GraphVisitor for UndirectedGraphVisitor {
+    type Output = UndirectedGraph;
+
+    fn begin(&mut self, header: &Header) -> Result<(), GraphFileError> {
+        self.edges.reserve(header.num_edges);
+        self.header = Some(header.clone());
+        Ok(())
+    }
+
+    fn edge(&mut self, from: i32, to: i32, _weight: Option<i32>) -> Result<(), GraphFileError> {
+        if from < to {
+            self.edges.push((from, to));
         }
+        Ok(())
+    }
+
+    fn end(self) -> Result<UndirectedGraph, GraphFileError> {
+        let header = self.header.expect("`begin` must be called before `end`");
 
         // Check edge size
-        if edges.len() != header.num_edges {
+        if self.edges.len() != header.num_edges {
             return Err(GraphFileError::EdgeSizeMissmatch {
-                actual: edges.len(),
+                actual: self.edges.len(),
                 header: header.num_edges,
             });
         }
         Ok(UndirectedGraph {
-            vertex_size: header.num_vertices as usize,
-            edges,
+            vertex_size: header.num_vertices,
+            edges: self.edges,
         })
     }
 }
 
+impl ToMetisGraphFormat for UndirectedGraph {
+    fn write_metis_graph(&self, mut w: impl Write) -> io::Result<()> {
+        let mut adjacency = vec![Vec::new(); self.vertex_size];
+        for &(i, j) in &self.edges {
+            adjacency[i as usize - 1].push(j);
+            adjacency[j as usize - 1].push(i);
+        }
+        let header = Header {
+            num_vertices: self.vertex_size,
+            num_edges: self.edges.len(),
+            fmt: Format::default(),
+            num_weights: 1,
+        };
+        writeln!(w, "{}", header)?;
+        for mut neighbours in adjacency {
+            neighbours.sort_unstable();
+            let line: Vec<String> = neighbours.iter().map(|v| v.to_string()).collect();
+            writeln!(w, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl UndirectedGraph {
+    /// Number of vertices
+    pub fn num_vertices(&self) -> usize {
+        self.vertex_size
+    }
+
+    /// Edges `(i, j)` with `i < j`
+    pub fn edges(&self) -> &[(i32, i32)] {
+        &self.edges
+    }
+}
+
 /// Compressed sparse row (CSR) format for general (non-symmetric) graph matrix
+///
+/// Vertex weights, vertex sizes and edge weights are optional, and stored in METIS layout.
+/// `xadj` and `adjncy` are 0-based unless the numbering is changed by [CSRGraph::with_numbering].
 #[derive(Debug, Clone, PartialEq)]
 pub struct CSRGraph {
     /// `adjncy` in METIS manual
-    column_indices: Vec<i32>,
+    pub(crate) column_indices: Vec<i32>,
     /// `xadj` in METIS manual
-    num_elements_in_row_cumsum: Vec<i32>,
+    pub(crate) num_elements_in_row_cumsum: Vec<i32>,
+    /// Numbering of `xadj` and `adjncy`
+    pub(crate) numbering: Numbering,
+    /// Number of weights of each vertex, `ncon` in METIS manual
+    pub(crate) ncon: usize,
+    /// `vwgt` in METIS manual, `ncon` weights for each vertex
+    pub(crate) vertex_weights: Option<Vec<i32>>,
+    /// `vsize` in METIS manual
+    pub(crate) vertex_sizes: Option<Vec<i32>>,
+    /// `adjwgt` in METIS manual, weight of each entry of `adjncy`
+    pub(crate) edge_weights: Option<Vec<i32>>,
 }
 
 impl CSRGraph {
-    fn new(header: &Header) -> Self {
+    /// Empty graph whose arrays are preallocated from the header of METIS graph file
+    pub(crate) fn new(header: &Header) -> Self {
+        let fmt = header.fmt;
+        let ncon = if fmt.has_vertex_weight {
+            header.num_weights
+        } else {
+            1
+        };
         CSRGraph {
             column_indices: Vec::with_capacity(2 * header.num_edges),
             num_elements_in_row_cumsum: Vec::with_capacity(header.num_vertices + 1),
+            numbering: Numbering::C,
+            ncon,
+            vertex_weights: fmt
+                .has_vertex_weight
+                .then(|| Vec::with_capacity(header.num_vertices * ncon)),
+            vertex_sizes: fmt
+                .has_vertex_size
+                .then(|| Vec::with_capacity(header.num_vertices)),
+            edge_weights: fmt
+                .has_edge_weight
+                .then(|| Vec::with_capacity(2 * header.num_edges)),
+        }
+    }
+
+    /// Read METIS graph file in memory using the byte-level parser
+    ///
+    /// This is much faster than [FromMetisGraphFormat::from_metis_graph_str]
+    /// for large graphs, and returns the same graph or the same error.
+    /// Compressed input is decompressed in memory (see [crate::io::compress]).
+    pub fn from_metis_graph_bytes(input: &[u8]) -> Result<Self, GraphFileError> {
+        let input = compress::decompress_bytes(input)?;
+        crate::io::bytes::parse_csr_graph(&input)
+    }
+
+    /// Read METIS graph file in memory, parsing chunks of lines in parallel
+    ///
+    /// This returns the same graph or the same error as [CSRGraph::from_metis_graph_bytes].
+    #[cfg(feature = "rayon")]
+    pub fn from_metis_graph_bytes_parallel(input: &[u8]) -> Result<Self, GraphFileError> {
+        let input = compress::decompress_bytes(input)?;
+        crate::io::bytes::parse_csr_graph_parallel(&input)
+    }
+
+    /// Read METIS graph file by memory-mapping it, and parse using the byte-level parser
+    ///
+    /// Compressed files are decompressed in memory as [CSRGraph::from_metis_graph_bytes] does.
+    ///
+    /// # Safety
+    /// The file must not be modified by other processes while reading.
+    #[cfg(feature = "mmap")]
+    pub unsafe fn from_metis_graph_mmap(path: impl AsRef<Path>) -> Result<Self, GraphFileError> {
+        let f = fs::File::open(path.as_ref())?;
+        let mmap = memmap2::Mmap::map(&f)?;
+        Self::from_metis_graph_bytes(&mmap)
+    }
+
+    /// Create from `xadj` and `adjncy` without checks
+    pub(crate) fn from_raw_parts(xadj: Vec<i32>, adjncy: Vec<i32>) -> Self {
+        CSRGraph {
+            column_indices: adjncy,
+            num_elements_in_row_cumsum: xadj,
+            numbering: Numbering::C,
+            ncon: 1,
+            vertex_weights: None,
+            vertex_sizes: None,
+            edge_weights: None,
+        }
+    }
+
+    /// Create from the sparsity pattern of a symmetric sparse matrix in CSR format
+    ///
+    /// Diagonal entries are dropped since METIS does not allow self-loops.
+    /// `row_ptr` must have `n + 1` non-decreasing elements from 0 to `col_ind.len()`,
+    /// and `col_ind` must be in `0..n`.
+    pub fn from_symmetric_pattern(row_ptr: &[i32], col_ind: &[i32]) -> error::Result<Self> {
+        let api_name = "CSRGraph::from_symmetric_pattern";
+        let n = match row_ptr.len().checked_sub(1) {
+            Some(n) => n,
+            None => {
+                return Err(error::Error::invalid_input(
+                    api_name,
+                    "row_ptr must have n + 1 elements",
+                ))
+            }
+        };
+        if row_ptr[0] != 0 {
+            return Err(error::Error::invalid_input(
+                api_name,
+                format!("row_ptr[0] must be 0, but is {}", row_ptr[0]),
+            ));
+        }
+        if let Some(row) = (0..n).find(|&row| row_ptr[row] > row_ptr[row + 1]) {
+            return Err(error::Error::invalid_input(
+                api_name,
+                format!(
+                    "row_ptr must be non-decreasing, but decreases at row {}",
+                    row
+                ),
+            ));
+        }
+        if row_ptr[n] as usize != col_ind.len() {
+            return Err(error::Error::invalid_input(
+                api_name,
+                format!(
+                    "row_ptr[n] must be the number of non-zero entries {}, but is {}",
+                    col_ind.len(),
+                    row_ptr[n]
+                ),
+            ));
+        }
+        if let Some((i, col)) = col_ind
+            .iter()
+            .enumerate()
+            .find(|(_, &col)| col < 0 || col as usize >= n)
+        {
+            return Err(error::Error::invalid_input(
+                api_name,
+                format!("col_ind[{}] must be in 0..{}, but is {}", i, n, col),
+            ));
+        }
+
+        let mut column_indices = Vec::with_capacity(col_ind.len());
+        let mut num_elements_in_row_cumsum = Vec::with_capacity(row_ptr.len());
+        num_elements_in_row_cumsum.push(0);
+        for (row, range) in row_ptr.windows(2).enumerate() {
+            for &col in &col_ind[range[0] as usize..range[1] as usize] {
+                if col != row as i32 {
+                    column_indices.push(col);
+                }
+            }
+            num_elements_in_row_cumsum.push(column_indices.len() as i32);
+        }
+        Ok(Self::from_raw_parts(
+            num_elements_in_row_cumsum,
+            column_indices,
+        ))
+    }
+
+    /// Convert `xadj` and `adjncy` into `numbering`
+    ///
+    /// METIS is called with `METIS_OPTION_NUMBERING` corresponding to the numbering of the graph,
+    /// and the results, e.g. part index and permutation, are always 0-based.
+    pub fn with_numbering(mut self, numbering: Numbering) -> Self {
+        let shift = numbering.offset() - self.numbering.offset();
+        if shift != 0 {
+            for i in self
+                .num_elements_in_row_cumsum
+                .iter_mut()
+                .chain(self.column_indices.iter_mut())
+            {
+                *i += shift;
+            }
+            self.numbering = numbering;
         }
+        self
+    }
+
+    /// Numbering of `xadj` and `adjncy`
+    pub fn numbering(&self) -> Numbering {
+        self.numbering
+    }
+
+    /// Copies of `xadj` and `adjncy` in `numbering`
+    pub(crate) fn to_raw_arrays(&self, numbering: Numbering) -> (Vec<i32>, Vec<i32>) {
+        let shift = numbering.offset() - self.numbering.offset();
+        let copy = |a: &[i32]| a.iter().map(|i| i + shift).collect();
+        (
+            copy(&self.num_elements_in_row_cumsum),
+            copy(&self.column_indices),
+        )
+    }
+
+    /// Set `ncon` weights for each vertex
+    pub fn with_vertex_weights(mut self, ncon: usize, vwgt: Vec<i32>) -> error::Result<Self> {
+        let api_name = "CSRGraph::with_vertex_weights";
+        if ncon == 0 {
+            return Err(error::Error::invalid_input(
+                api_name,
+                "ncon must be positive",
+            ));
+        }
+        error::check_len(api_name, "vwgt", vwgt.len(), self.num_vertices() * ncon)?;
+        self.ncon = ncon;
+        self.vertex_weights = Some(vwgt);
+        Ok(self)
+    }
+
+    /// Set size of each vertex used for computing the total communication volume
+    pub fn with_vertex_sizes(mut self, vsize: Vec<i32>) -> error::Result<Self> {
+        error::check_len(
+            "CSRGraph::with_vertex_sizes",
+            "vsize",
+            vsize.len(),
+            self.num_vertices(),
+        )?;
+        self.vertex_sizes = Some(vsize);
+        Ok(self)
+    }
+
+    /// Set weight of each edge, in the same order as `adjncy`
+    pub fn with_edge_weights(mut self, adjwgt: Vec<i32>) -> error::Result<Self> {
+        error::check_len(
+            "CSRGraph::with_edge_weights",
+            "adjwgt",
+            adjwgt.len(),
+            self.column_indices.len(),
+        )?;
+        self.edge_weights = Some(adjwgt);
+        Ok(self)
+    }
+
+    /// Number of vertices, `nvtxs` in METIS manual
+    pub fn num_vertices(&self) -> usize {
+        self.num_elements_in_row_cumsum.len() - 1
+    }
+
+    /// `xadj` in METIS manual
+    pub fn xadj(&self) -> &[i32] {
+        &self.num_elements_in_row_cumsum
+    }
+
+    /// `adjncy` in METIS manual
+    pub fn adjncy(&self) -> &[i32] {
+        &self.column_indices
+    }
+
+    /// Number of weights of each vertex, `ncon` in METIS manual. One if vertices are not weighted.
+    pub fn ncon(&self) -> usize {
+        self.ncon
+    }
+
+    /// `vwgt` in METIS manual, `ncon` weights for each vertex
+    pub fn vwgt(&self) -> Option<&[i32]> {
+        self.vertex_weights.as_deref()
+    }
+
+    /// `vsize` in METIS manual
+    pub fn vsize(&self) -> Option<&[i32]> {
+        self.vertex_sizes.as_deref()
+    }
+
+    /// `adjwgt` in METIS manual
+    pub fn adjwgt(&self) -> Option<&[i32]> {
+        self.edge_weights.as_deref()
+    }
+}
+
+/// Structural problem of a graph found by [CSRGraph::validate]
+///
+/// Vertex indices are 0-based regardless of the numbering of the graph.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum GraphProblem {
+    #[error("xadj is not non-decreasing or inconsistent with adjncy at vertex {vertex}")]
+    InvalidXadj { vertex: usize },
+
+    #[error("Neighbour {neighbour} of vertex {vertex} is out-of-range")]
+    OutOfRange { vertex: usize, neighbour: i32 },
+
+    #[error("Vertex {vertex} contains a self-loop")]
+    SelfLoop { vertex: usize },
+
+    #[error("Edge ({from}, {to}) exists, but ({to}, {from}) does not")]
+    Asymmetric { from: usize, to: usize },
+
+    #[error("Edge ({from}, {to}) is repeated")]
+    Duplicated { from: usize, to: usize },
+
+    #[error("Edges ({from}, {to}) and ({to}, {from}) have different weights: {weight} != {reverse_weight}")]
+    WeightMismatch {
+        from: usize,
+        to: usize,
+        weight: i32,
+        reverse_weight: i32,
+    },
+}
+
+impl CSRGraph {
+    /// Check the structure of the graph as `graphchk` program of METIS does
+    ///
+    /// Returns all problems found, and the graph is valid if it is empty.
+    pub fn validate(&self) -> Vec<GraphProblem> {
+        let n = self.num_vertices();
+        let (xadj, adjncy) = self.to_raw_arrays(Numbering::C);
+        if xadj[0] != 0 {
+            return vec![GraphProblem::InvalidXadj { vertex: 0 }];
+        }
+        for v in 0..n {
+            if xadj[v] > xadj[v + 1] || xadj[v + 1] as usize > adjncy.len() {
+                return vec![GraphProblem::InvalidXadj { vertex: v }];
+            }
+        }
+        if xadj[n] as usize != adjncy.len() {
+            return vec![GraphProblem::InvalidXadj { vertex: n }];
+        }
+        let neighbours = |v: usize| xadj[v] as usize..xadj[v + 1] as usize;
+        let in_range = |w: i32| w >= 0 && (w as usize) < n;
+
+        // Edges `(v, i)` with `adjncy[i] = w` pointing to each vertex `w`, i.e. the transpose in CSR format
+        let mut incoming_ptr = vec![0; n + 1];
+        for &w in adjncy.iter().filter(|&&w| in_range(w)) {
+            incoming_ptr[w as usize + 1] += 1;
+        }
+        for w in 0..n {
+            incoming_ptr[w + 1] += incoming_ptr[w];
+        }
+        let mut incoming = vec![(0, 0); incoming_ptr[n]];
+        let mut next = incoming_ptr.clone();
+        for v in 0..n {
+            for i in neighbours(v).filter(|&i| in_range(adjncy[i])) {
+                let w = adjncy[i] as usize;
+                incoming[next[w]] = (v, i);
+                next[w] += 1;
+            }
+        }
+
+        // `reverse[i]` is the first position of `(w, v)` in `adjncy` for the edge `i = (v, w)`,
+        // found by marking the positions of the neighbours of each `w` in O(m) total
+        let mut reverse = vec![None; adjncy.len()];
+        let mut position = vec![None; n];
+        for w in 0..n {
+            for j in neighbours(w).rev().filter(|&j| in_range(adjncy[j])) {
+                position[adjncy[j] as usize] = Some(j);
+            }
+            for &(v, i) in &incoming[incoming_ptr[w]..incoming_ptr[w + 1]] {
+                reverse[i] = position[v];
+            }
+            for j in neighbours(w).filter(|&j| in_range(adjncy[j])) {
+                position[adjncy[j] as usize] = None;
+            }
+        }
+
+        let mut problems = Vec::new();
+        let mut marker = vec![false; n];
+        for v in 0..n {
+            for i in neighbours(v) {
+                let w = adjncy[i];
+                if w < 0 || w as usize >= n {
+                    problems.push(GraphProblem::OutOfRange {
+                        vertex: v,
+                        neighbour: w,
+                    });
+                    continue;
+                }
+                let w = w as usize;
+                if marker[w] {
+                    problems.push(GraphProblem::Duplicated { from: v, to: w });
+                }
+                marker[w] = true;
+
+                if v == w {
+                    problems.push(GraphProblem::SelfLoop { vertex: v });
+                    continue;
+                }
+                match reverse[i] {
+                    Some(j) => {
+                        if let Some(adjwgt) = &self.edge_weights {
+                            // Report from the smaller end, i.e. for each occurrence of `(v, w)` with `v < w`
+                            if v < w && adjwgt[i] != adjwgt[j] {
+                                problems.push(GraphProblem::WeightMismatch {
+                                    from: v,
+                                    to: w,
+                                    weight: adjwgt[i],
+                                    reverse_weight: adjwgt[j],
+                                });
+                            }
+                        }
+                    }
+                    None => problems.push(GraphProblem::Asymmetric { from: v, to: w }),
+                }
+            }
+            for i in neighbours(v) {
+                if let Some(m) = marker.get_mut(adjncy[i] as usize) {
+                    *m = false;
+                }
+            }
+        }
+        problems
+    }
+
+    /// Validate the graph and its weights before passing to METIS routine `api_name`
+    pub(crate) fn check_input(&self, api_name: &str) -> error::Result<()> {
+        // Weights are read by METIS for each vertex and edge, and by `validate` for edges
+        let n = self.num_vertices();
+        for (name, weights, expected) in [
+            ("vwgt", self.vwgt(), n * self.ncon),
+            ("vsize", self.vsize(), n),
+            ("adjwgt", self.adjwgt(), self.column_indices.len()),
+        ] {
+            if let Some(weights) = weights {
+                error::check_len(api_name, name, weights.len(), expected)?;
+            }
+        }
+
+        let problems = self.validate();
+        if let Some(problem) = problems.first() {
+            let reason = if problems.len() > 1 {
+                format!("{} (and {} more problems)", problem, problems.len() - 1)
+            } else {
+                problem.to_string()
+            };
+            return Err(error::Error::invalid_input(api_name, reason));
+        }
+        for (name, weights) in [
+            ("vwgt", self.vwgt()),
+            ("vsize", self.vsize()),
+            ("adjwgt", self.adjwgt()),
+        ] {
+            if let Some(weights) = weights {
+                error::check_non_negative(api_name, name, weights)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fix the graph as `graphchk` program of METIS does
+    ///
+    /// Self-loops and out-of-range neighbours are removed,
+    /// and asymmetric edges are symmetrized and repeated edges are merged.
+    /// The smallest weight is kept for the edges with different weights.
+    /// `xadj` must be valid, i.e. [GraphProblem::InvalidXadj] is not reported.
+    pub fn repair(self) -> Self {
+        let n = self.num_vertices();
+        let numbering = self.numbering;
+        let (xadj, adjncy) = self.to_raw_arrays(Numbering::C);
+
+        // Keep only the upper-triangular part of the adjacency matrix
+        let mut edges = Vec::with_capacity(adjncy.len());
+        for v in 0..n {
+            for i in xadj[v] as usize..xadj[v + 1] as usize {
+                let w = adjncy[i];
+                if w < 0 || w as usize >= n || w as usize == v {
+                    continue;
+                }
+                let weight = self.edge_weights.as_ref().map_or(1, |adjwgt| adjwgt[i]);
+                let (u, w) = (v.min(w as usize), v.max(w as usize));
+                edges.push((u, w, weight));
+            }
+        }
+        edges.sort_unstable();
+        edges.dedup_by_key(|&mut (u, w, _)| (u, w));
+
+        let mut degree = vec![0; n + 1];
+        for &(u, w, _) in &edges {
+            degree[u + 1] += 1;
+            degree[w + 1] += 1;
+        }
+        for v in 0..n {
+            degree[v + 1] += degree[v];
+        }
+        let new_xadj = degree.clone();
+        let mut new_adjncy = vec![0; 2 * edges.len()];
+        let mut new_adjwgt = vec![0; 2 * edges.len()];
+        for &(u, w, weight) in &edges {
+            for (from, to) in [(u, w), (w, u)] {
+                let i = degree[from] as usize;
+                new_adjncy[i] = to as i32;
+                new_adjwgt[i] = weight;
+                degree[from] += 1;
+            }
+        }
+
+        CSRGraph {
+            column_indices: new_adjncy,
+            num_elements_in_row_cumsum: new_xadj,
+            numbering: Numbering::C,
+            edge_weights: self.edge_weights.map(|_| new_adjwgt),
+            ..self
+        }
+        .with_numbering(numbering)
     }
 }
 
 impl FromMetisGraphFormat for CSRGraph {
-    fn from_metis_graph_iter(
-        header: &Header,
-        lines: impl Iterator<Item = Result<Line, LineError>>,
-    ) -> Result<Self, GraphFileError> {
-        let mut graph = Self::new(header);
-        let mut num_elements = 0;
-        graph.num_elements_in_row_cumsum.push(num_elements);
-        for line in lines {
-            let line = line?;
-            num_elements += line.vertices.len() as i32;
-            for vertex in line.vertices {
-                graph.column_indices.push(vertex);
-            }
-            graph.num_elements_in_row_cumsum.push(num_elements);
-        }
-        if graph.num_elements_in_row_cumsum.len() != header.num_vertices + 1 {
+    type Visitor = CSRGraphVisitor;
+}
+
+/// [GraphVisitor] constructing [CSRGraph]
+#[derive(Debug, Default)]
+pub struct CSRGraphVisitor {
+    state: Option<(Header, CSRGraph)>,
+}
+
+impl GraphVisitor for CSRGraphVisitor {
+    type Output = CSRGraph;
+
+    fn begin(&mut self, header: &Header) -> Result<(), GraphFileError> {
+        let mut graph = CSRGraph::new(header);
+        graph.num_elements_in_row_cumsum.push(0);
+        self.state = Some((header.clone(), graph));
+        Ok(())
+    }
+
+    fn vertex(&mut self, line: &Line) -> Result<(), GraphFileError> {
+        let (_, graph) = self
+            .state
+            .as_mut()
+            .expect("`begin` must be called before `vertex`");
+        graph.push_line(line);
+        Ok(())
+    }
+
+    fn end(self) -> Result<CSRGraph, GraphFileError> {
+        let (header, graph) = self.state.expect("`begin` must be called before `end`");
+        graph.check_size(&header)?;
+        Ok(graph)
+    }
+}
+
+impl CSRGraph {
+    /// Append a vertex read from METIS graph file
+    pub(crate) fn push_line(&mut self, line: &Line) {
+        for &vertex in &line.vertices {
+            // 1-based in file
+            self.column_indices.push(vertex - 1);
+        }
+        self.num_elements_in_row_cumsum
+            .push(self.column_indices.len() as i32);
+        if let (Some(vwgt), Some(ws)) = (self.vertex_weights.as_mut(), &line.vertex_weights) {
+            vwgt.extend_from_slice(ws);
+        }
+        if let (Some(vsize), Some(s)) = (self.vertex_sizes.as_mut(), line.vertex_size) {
+            vsize.push(s);
+        }
+        if let (Some(adjwgt), Some(ws)) = (self.edge_weights.as_mut(), &line.edge_weights) {
+            adjwgt.extend_from_slice(ws);
+        }
+    }
+
+    /// Check the numbers of vertices and edges read from METIS graph file match the header
+    pub(crate) fn check_size(&self, header: &Header) -> Result<(), GraphFileError> {
+        if self.num_elements_in_row_cumsum.len() != header.num_vertices + 1 {
             return Err(GraphFileError::VertexSizeMissing {
-                actual: graph.num_elements_in_row_cumsum.len() - 1,
+                actual: self.num_elements_in_row_cumsum.len() - 1,
                 header: header.num_vertices,
             });
         }
-        if graph.column_indices.len() != 2 * header.num_edges {
+        if self.column_indices.len() != 2 * header.num_edges {
             return Err(GraphFileError::EdgeSizeMissmatch {
-                actual: graph.column_indices.len() / 2,
+                actual: self.column_indices.len() / 2,
                 header: header.num_edges,
             });
         }
-        Ok(graph)
+        Ok(())
     }
 }
 
+impl ToMetisGraphFormat for CSRGraph {
+    /// Write with 1-based vertex indices regardless of the numbering of the graph
+    fn write_metis_graph(&self, mut w: impl Write) -> io::Result<()> {
+        let fmt = Format {
+            has_vertex_size: self.vertex_sizes.is_some(),
+            has_vertex_weight: self.vertex_weights.is_some(),
+            has_edge_weight: self.edge_weights.is_some(),
+        };
+        let header = Header {
+            num_vertices: self.num_vertices(),
+            num_edges: self.column_indices.len() / 2,
+            fmt,
+            num_weights: self.ncon,
+        };
+        writeln!(w, "{}", header)?;
+        let offset = self.numbering.offset();
+        let mut line = Vec::new();
+        for v in 0..self.num_vertices() {
+            line.clear();
+            if let Some(vsize) = &self.vertex_sizes {
+                line.push(vsize[v]);
+            }
+            if let Some(vwgt) = &self.vertex_weights {
+                line.extend_from_slice(&vwgt[v * self.ncon..(v + 1) * self.ncon]);
+            }
+            let range = (self.num_elements_in_row_cumsum[v] - offset) as usize
+                ..(self.num_elements_in_row_cumsum[v + 1] - offset) as usize;
+            for i in range {
+                line.push(self.column_indices[i] - offset + 1);
+                if let Some(adjwgt) = &self.edge_weights {
+                    line.push(adjwgt[i]);
+                }
+            }
+            let line: Vec<String> = line.iter().map(|x| x.to_string()).collect();
+            writeln!(w, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Error for converting floating-point weights into integer weights
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
+pub enum WeightConversionError {
+    #[error("Scale must be positive and finite: {scale}")]
+    InvalidScale { scale: f32 },
+
+    #[error("Weight {index} must be non-negative and finite: {weight}")]
+    InvalidWeight { index: usize, weight: f32 },
+
+    #[error("Weight {index} overflows after scaling by {scale}: {weight}")]
+    Overflow {
+        index: usize,
+        weight: f32,
+        scale: f32,
+    },
+}
+
+/// Integer weights converted from floating-point weights by [round_weights]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundedWeights {
+    /// `round(weight * scale)` for each weight
+    pub weights: Vec<i32>,
+    /// Scale factor multiplied before rounding
+    pub scale: f32,
+    /// Maximum of `|weights[i] / scale - weight[i]|`, i.e. error in the unit of the input weights
+    pub max_error: f32,
+}
+
+/// Convert floating-point weights into integer weights by scaling and rounding
+///
+/// METIS accepts only integer weights. Larger `scale` keeps more precision,
+/// but the sum of weights must fit in `i32` for METIS.
+pub fn round_weights(weights: &[f32], scale: f32) -> Result<RoundedWeights, WeightConversionError> {
+    if !(scale.is_finite() && scale > 0.0) {
+        return Err(WeightConversionError::InvalidScale { scale });
+    }
+    let mut max_error = 0.0_f64;
+    let weights = weights
+        .iter()
+        .enumerate()
+        .map(|(index, &weight)| {
+            if !(weight.is_finite() && weight >= 0.0) {
+                return Err(WeightConversionError::InvalidWeight { index, weight });
+            }
+            let rounded = (weight as f64 * scale as f64).round();
+            if rounded > i32::MAX as f64 {
+                return Err(WeightConversionError::Overflow {
+                    index,
+                    weight,
+                    scale,
+                });
+            }
+            max_error = max_error.max((rounded / scale as f64 - weight as f64).abs());
+            Ok(rounded as i32)
+        })
+        .collect::<Result<Vec<i32>, _>>()?;
+    Ok(RoundedWeights {
+        weights,
+        scale,
+        max_error: max_error as f32,
+    })
+}
+
+/// Convert floating-point weights into integer weights whose maximum is `max_weight`
+///
+/// See [round_weights] for details.
+pub fn round_weights_to_max(
+    weights: &[f32],
+    max_weight: i32,
+) -> Result<RoundedWeights, WeightConversionError> {
+    let max = weights.iter().cloned().fold(0.0_f32, f32::max);
+    let scale = if max > 0.0 {
+        max_weight as f32 / max
+    } else {
+        1.0
+    };
+    round_weights(weights, scale)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     mod undirected {
         use super::*;
+
+        fn sorted_edges(graph: &UndirectedGraph) -> Vec<(i32, i32)> {
+            let mut edges = graph.edges().to_vec();
+            edges.sort_unstable();
+            edges
+        }
+
         #[test]
         fn manual_2a() {
             let _graph = UndirectedGraph::from_metis_graph_str(examples::MANUAL_2A).unwrap();
@@ -111,6 +816,34 @@ mod tests {
         fn manual_2d() {
             let _graph = UndirectedGraph::from_metis_graph_str(examples::MANUAL_2D).unwrap();
         }
+
+        #[test]
+        fn write_manual_2a() {
+            let graph = UndirectedGraph::from_metis_graph_str(examples::MANUAL_2A).unwrap();
+            let written = graph.to_metis_graph_string();
+            assert_eq!(written.lines().next(), Some("7 11"));
+            // Neighbours are sorted in the written file
+            assert_eq!(written.lines().nth(3), Some("1 2 4 5"));
+            let read = UndirectedGraph::from_metis_graph_str(&written).unwrap();
+            assert_eq!(read.num_vertices(), graph.num_vertices());
+            assert_eq!(sorted_edges(&read), sorted_edges(&graph));
+        }
+
+        #[test]
+        fn round_trip() {
+            for example in [
+                examples::MANUAL_2A,
+                examples::MANUAL_2B,
+                examples::MANUAL_2C,
+                examples::MANUAL_2D,
+            ] {
+                let graph = UndirectedGraph::from_metis_graph_str(example).unwrap();
+                let written = graph.to_metis_graph_string();
+                let read = UndirectedGraph::from_metis_graph_str(&written).unwrap();
+                assert_eq!(read.num_vertices(), graph.num_vertices());
+                assert_eq!(sorted_edges(&read), sorted_edges(&graph));
+            }
+        }
     }
 
     mod csr {
@@ -125,11 +858,424 @@ mod tests {
         }
         #[test]
         fn manual_2c() {
-            let _graph = CSRGraph::from_metis_graph_str(examples::MANUAL_2C).unwrap();
+            let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_2C).unwrap();
+            assert_eq!(graph.ncon(), 1);
+            assert_eq!(graph.vsize(), None);
+            assert_eq!(graph.vwgt().unwrap(), &[4, 2, 5, 3, 1, 6, 2]);
+            assert_eq!(graph.adjwgt().unwrap().len(), graph.adjncy().len());
+            assert_eq!(&graph.adjwgt().unwrap()[..3], &[1, 2, 1]);
         }
         #[test]
         fn manual_2d() {
-            let _graph = CSRGraph::from_metis_graph_str(examples::MANUAL_2D).unwrap();
+            let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_2D).unwrap();
+            assert_eq!(graph.ncon(), 3);
+            assert_eq!(&graph.vwgt().unwrap()[..6], &[1, 2, 0, 0, 2, 2]);
+            assert_eq!(graph.vwgt().unwrap().len(), 7 * 3);
+            assert_eq!(graph.adjwgt(), None);
+        }
+
+        #[test]
+        fn with_weights() {
+            let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A)
+                .unwrap()
+                .with_vertex_weights(2, vec![1; 30])
+                .unwrap()
+                .with_vertex_sizes(vec![2; 15])
+                .unwrap()
+                .with_edge_weights(vec![3; 44])
+                .unwrap();
+            assert_eq!(graph.ncon(), 2);
+            assert_eq!(graph.vwgt().unwrap().len(), 30);
+            assert_eq!(graph.vsize().unwrap().len(), 15);
+            assert_eq!(graph.adjwgt().unwrap().len(), 44);
+
+            let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+            for err in [
+                graph.clone().with_vertex_weights(0, vec![]).unwrap_err(),
+                graph
+                    .clone()
+                    .with_vertex_weights(2, vec![1; 15])
+                    .unwrap_err(),
+                graph.clone().with_vertex_sizes(vec![2; 14]).unwrap_err(),
+                graph.with_edge_weights(vec![3; 22]).unwrap_err(),
+            ] {
+                assert!(matches!(err, error::Error::InvalidInput { .. }));
+            }
+        }
+
+        #[test]
+        fn short_format() {
+            let graph = CSRGraph::from_metis_graph_str("3 2 11\n5 2 1\n1 1 1 3 2\n2 3 2").unwrap();
+            assert_eq!(graph.vwgt().unwrap(), &[5, 1, 2]);
+            assert_eq!(graph.adjwgt().unwrap(), &[1, 1, 2, 2]);
+        }
+
+        #[test]
+        fn comments() {
+            let graph = CSRGraph::from_metis_graph_str(
+                "% header comment\n3 2\n% vertex comment\n2\n1 3\n  % indented comment\n2",
+            )
+            .unwrap();
+            assert_eq!(graph.xadj(), &[0, 1, 3, 4]);
+            assert_eq!(graph.adjncy(), &[1, 0, 2, 1]);
+        }
+
+        #[test]
+        fn isolated_vertices() {
+            // Vertex 2 and the last vertex 4 are isolated
+            let graph = CSRGraph::from_metis_graph_str("4 1\n3\n\n1\n\n").unwrap();
+            assert_eq!(graph.num_vertices(), 4);
+            assert_eq!(graph.xadj(), &[0, 1, 1, 2, 2]);
+
+            // Blank lines after the last vertex are ignored
+            let graph = CSRGraph::from_metis_graph_str("3 1\n3\n\n1\n\n  \n").unwrap();
+            assert_eq!(graph.xadj(), &[0, 1, 1, 2]);
+
+            let err = CSRGraph::from_metis_graph_str("4 1\n3\n\n1").unwrap_err();
+            assert!(matches!(
+                err,
+                GraphFileError::VertexSizeMissing {
+                    actual: 3,
+                    header: 4
+                }
+            ));
+        }
+
+        #[test]
+        fn error_line_number() {
+            let err =
+                CSRGraph::from_metis_graph_str("% comment\n3 2\n% comment\n2\n1 x\n2").unwrap_err();
+            assert!(matches!(
+                err,
+                GraphFileError::InvalidLineAt {
+                    line_number: 5,
+                    error: LineError::ParseIntError(_)
+                }
+            ));
+
+            let err =
+                CSRGraph::from_metis_graph_str("% comment\n\n3 2 012\n2\n1 3\n2").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Invalid header at line 3: Format spec in header is invalid: 012"
+            );
+            assert!(matches!(
+                err,
+                GraphFileError::InvalidHeaderAt {
+                    line_number: 3,
+                    error: HeaderError::InvalidFormat { .. }
+                }
+            ));
+        }
+
+        #[test]
+        fn reader() {
+            let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+            let input = examples::MANUAL_3A.replace('\n', "\r\n");
+            assert_eq!(
+                CSRGraph::from_metis_graph_reader(input.as_bytes()).unwrap(),
+                graph
+            );
+        }
+
+        #[test]
+        fn invalid_encoding() {
+            let input = b"3 2\n2\n1 3\xff\n2\n";
+            let err = CSRGraph::from_metis_graph_reader(&input[..]).unwrap_err();
+            assert!(matches!(
+                err,
+                GraphFileError::InvalidEncoding { line_number: 3, .. }
+            ));
+        }
+
+        #[test]
+        fn read_error() {
+            /// Fails after reading the header
+            struct Failing<'a>(&'a [u8]);
+            impl io::Read for Failing<'_> {
+                fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                    if self.0.is_empty() {
+                        return Err(io::Error::other("disk failure"));
+                    }
+                    io::Read::read(&mut self.0, buf)
+                }
+            }
+            let reader = io::BufReader::new(Failing(b"3 2\n2\n"));
+            let err = CSRGraph::from_metis_graph_reader(reader).unwrap_err();
+            assert!(matches!(err, GraphFileError::IO(e) if e.to_string() == "disk failure"));
+
+            let reader = io::BufReader::new(Failing(b""));
+            let err = CSRGraph::from_metis_graph_reader(reader).unwrap_err();
+            assert!(matches!(err, GraphFileError::IO(_)));
+        }
+
+        #[test]
+        fn write_grid() {
+            let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+            let written = graph.to_metis_graph_string();
+            let mut lines = written.lines();
+            assert_eq!(lines.next(), Some("15 22"));
+            assert_eq!(lines.next(), Some("2 6"));
+            assert_eq!(lines.next(), Some("1 3 7"));
+            assert_eq!(lines.last(), Some("10 14"));
+
+            assert_eq!(CSRGraph::from_metis_graph_str(&written).unwrap(), graph);
+
+            // Written file does not depend on the numbering
+            let fortran = graph.clone().with_numbering(Numbering::Fortran);
+            assert_eq!(fortran.to_metis_graph_string(), written);
+        }
+
+        #[test]
+        fn numbering() {
+            let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_2A).unwrap();
+            assert_eq!(graph.numbering(), Numbering::C);
+            assert_eq!(&graph.xadj()[..3], &[0, 3, 6]);
+            assert_eq!(&graph.adjncy()[..3], &[4, 2, 1]);
+
+            let fortran = graph.clone().with_numbering(Numbering::Fortran);
+            assert_eq!(fortran.numbering(), Numbering::Fortran);
+            assert_eq!(&fortran.xadj()[..3], &[1, 4, 7]);
+            assert_eq!(&fortran.adjncy()[..3], &[5, 3, 2]);
+            assert_eq!(fortran.with_numbering(Numbering::C), graph);
+        }
+
+        #[test]
+        fn round_trip() {
+            for example in [
+                examples::MANUAL_2A,
+                examples::MANUAL_2B,
+                examples::MANUAL_2C,
+                examples::MANUAL_2D,
+                examples::MANUAL_3A,
+            ] {
+                let graph = CSRGraph::from_metis_graph_str(example).unwrap();
+                let written = graph.to_metis_graph_string();
+                assert_eq!(CSRGraph::from_metis_graph_str(&written).unwrap(), graph);
+            }
+        }
+
+        #[test]
+        fn write_weighted() {
+            let vwgt = (0..30).collect();
+            let vsize = (0..15).map(|i| i % 3).collect();
+            let adjwgt = (0..44).map(|i| i % 5).collect();
+            let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A)
+                .unwrap()
+                .with_vertex_weights(2, vwgt)
+                .unwrap()
+                .with_vertex_sizes(vsize)
+                .unwrap()
+                .with_edge_weights(adjwgt)
+                .unwrap();
+            let written = graph.to_metis_graph_string();
+            let mut lines = written.lines();
+            assert_eq!(lines.next(), Some("15 22 111 2"));
+            assert_eq!(lines.next(), Some("0 0 1 2 0 6 1"));
+
+            let read = CSRGraph::from_metis_graph_str(&written).unwrap();
+            assert_eq!(read.ncon(), 2);
+            assert_eq!(read.vwgt(), graph.vwgt());
+            assert_eq!(read.vsize(), graph.vsize());
+            assert_eq!(read.adjwgt(), graph.adjwgt());
+
+            // Single vertex weight does not write `ncon`
+            let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A)
+                .unwrap()
+                .with_edge_weights(vec![1; 44])
+                .unwrap();
+            assert_eq!(
+                graph.to_metis_graph_string().lines().next(),
+                Some("15 22 001")
+            );
+        }
+
+        #[test]
+        fn round_trip_weights() {
+            for example in [
+                examples::MANUAL_2B,
+                examples::MANUAL_2C,
+                examples::MANUAL_2D,
+            ] {
+                let graph = CSRGraph::from_metis_graph_str(example).unwrap();
+                let header = example.trim().lines().next().unwrap().trim();
+                let written = graph.to_metis_graph_string();
+                assert_eq!(written.lines().next(), Some(header));
+            }
+        }
+
+        #[test]
+        fn validate() {
+            for example in [
+                examples::MANUAL_2A,
+                examples::MANUAL_2B,
+                examples::MANUAL_2C,
+                examples::MANUAL_2D,
+                examples::MANUAL_3A,
+            ] {
+                let graph = CSRGraph::from_metis_graph_str(example).unwrap();
+                assert!(graph.validate().is_empty());
+                assert!(graph
+                    .with_numbering(Numbering::Fortran)
+                    .validate()
+                    .is_empty());
+            }
+
+            // 0: [1, 1, 2], 1: [0, 1], 2: [3], 3: []
+            let graph = CSRGraph::from_raw_parts(vec![0, 3, 5, 6, 6], vec![1, 1, 2, 0, 1, 4])
+                .with_edge_weights(vec![1, 1, 1, 2, 1, 1])
+                .unwrap();
+            assert_eq!(
+                graph.validate(),
+                vec![
+                    GraphProblem::WeightMismatch {
+                        from: 0,
+                        to: 1,
+                        weight: 1,
+                        reverse_weight: 2
+                    },
+                    GraphProblem::Duplicated { from: 0, to: 1 },
+                    GraphProblem::WeightMismatch {
+                        from: 0,
+                        to: 1,
+                        weight: 1,
+                        reverse_weight: 2
+                    },
+                    GraphProblem::Asymmetric { from: 0, to: 2 },
+                    GraphProblem::SelfLoop { vertex: 1 },
+                    GraphProblem::OutOfRange {
+                        vertex: 2,
+                        neighbour: 4
+                    },
+                ]
+            );
+
+            let graph = CSRGraph::from_raw_parts(vec![0, 2, 1], vec![1, 0]);
+            assert_eq!(
+                graph.validate(),
+                vec![GraphProblem::InvalidXadj { vertex: 1 }]
+            );
+        }
+
+        #[test]
+        fn validate_star() {
+            // Hub vertex 0 connected to all others, which is quadratic to check by searching reverse edges
+            let n = 200_000;
+            let mut xadj = vec![0, n as i32 - 1];
+            let mut adjncy: Vec<i32> = (1..n as i32).collect();
+            for v in 1..n {
+                adjncy.push(0);
+                xadj.push(xadj[v] + 1);
+            }
+            let graph = CSRGraph::from_raw_parts(xadj, adjncy);
+            assert!(graph.validate().is_empty());
+        }
+
+        #[test]
+        fn check_input() {
+            let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+            assert!(graph.check_input("METIS_Test").is_ok());
+
+            let graph = CSRGraph::from_raw_parts(vec![0, 1, 2], vec![0, 0]);
+            let err = graph.check_input("METIS_Test").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Invalid input for METIS routine (METIS_Test): Vertex 0 contains a self-loop (and 1 more problems)"
+            );
+
+            let graph = CSRGraph::from_raw_parts(vec![0, 1, 2], vec![1, 0])
+                .with_vertex_weights(1, vec![1, -1])
+                .unwrap();
+            let err = graph.check_input("METIS_Test").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Invalid input for METIS routine (METIS_Test): vwgt[1] must be non-negative, but is -1"
+            );
+
+            let graph = CSRGraph::from_raw_parts(vec![0, 1, 2], vec![1, 0]);
+            for (graph, name) in [
+                (
+                    CSRGraph {
+                        ncon: 2,
+                        vertex_weights: Some(vec![1, 1, 1]),
+                        ..graph.clone()
+                    },
+                    "vwgt",
+                ),
+                (
+                    CSRGraph {
+                        vertex_sizes: Some(vec![1]),
+                        ..graph.clone()
+                    },
+                    "vsize",
+                ),
+                (
+                    CSRGraph {
+                        edge_weights: Some(vec![1, 1, 1]),
+                        ..graph.clone()
+                    },
+                    "adjwgt",
+                ),
+            ] {
+                let err = graph.check_input("METIS_Test").unwrap_err();
+                assert!(err.to_string().contains(name), "{}", err);
+            }
+        }
+
+        #[test]
+        fn repair() {
+            let graph = CSRGraph::from_raw_parts(vec![0, 3, 5, 6, 6], vec![1, 1, 2, 0, 1, 4])
+                .with_edge_weights(vec![3, 3, 1, 2, 1, 1])
+                .unwrap()
+                .with_numbering(Numbering::Fortran);
+            let repaired = graph.repair();
+            assert!(repaired.validate().is_empty());
+            assert_eq!(repaired.numbering(), Numbering::Fortran);
+            let repaired = repaired.with_numbering(Numbering::C);
+            assert_eq!(repaired.xadj(), &[0, 2, 3, 4, 4]);
+            assert_eq!(repaired.adjncy(), &[1, 2, 0, 0]);
+            assert_eq!(repaired.adjwgt().unwrap(), &[2, 1, 2, 1]);
+
+            // Valid graph is not changed
+            let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+            let repaired = graph.clone().repair();
+            assert_eq!(repaired.xadj(), graph.xadj());
+            assert!(repaired.validate().is_empty());
+        }
+
+        #[test]
+        fn float_weights() {
+            let rounded = round_weights(&[0.5, 1.24, 2.0], 10.0).unwrap();
+            assert_eq!(rounded.weights, vec![5, 12, 20]);
+            assert!((rounded.max_error - 0.04).abs() < 1e-6);
+
+            let rounded = round_weights_to_max(&[0.5, 1.0, 0.25], 100).unwrap();
+            assert_eq!(rounded.weights, vec![50, 100, 25]);
+            assert_eq!(rounded.max_error, 0.0);
+
+            assert_eq!(
+                round_weights(&[1.0, -1.0], 1.0).unwrap_err(),
+                WeightConversionError::InvalidWeight {
+                    index: 1,
+                    weight: -1.0
+                }
+            );
+            assert!(matches!(
+                round_weights(&[1.0, f32::NAN], 1.0).unwrap_err(),
+                WeightConversionError::InvalidWeight { index: 1, .. }
+            ));
+            assert_eq!(
+                round_weights(&[1e10], 1.0).unwrap_err(),
+                WeightConversionError::Overflow {
+                    index: 0,
+                    weight: 1e10,
+                    scale: 1.0
+                }
+            );
+            assert_eq!(
+                round_weights(&[1.0], 0.0).unwrap_err(),
+                WeightConversionError::InvalidScale { scale: 0.0 }
+            );
         }
 
         #[test]
@@ -144,6 +1290,11 @@ mod tests {
                 num_elements_in_row_cumsum: vec![
                     0, 2, 5, 8, 11, 13, 16, 20, 24, 28, 31, 33, 36, 39, 42, 44,
                 ],
+                numbering: Numbering::C,
+                ncon: 1,
+                vertex_weights: None,
+                vertex_sizes: None,
+                edge_weights: None,
             };
             assert_eq!(graph, ans);
         }
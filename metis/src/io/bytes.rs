@@ -0,0 +1,538 @@
+//! Byte-level parser of METIS graph format
+//!
+//! [parse_csr_graph] reads a graph file in memory without allocating for each line,
+//! and writes directly into the arrays of [CSRGraph] preallocated from the header.
+//! Lines which the fast path does not handle, e.g. weights written as `3.0` or non-ASCII text,
+//! are parsed by [Line::parse] as [FromMetisGraphFormat] does,
+//! so that both return the same graph and the same error for any input.
+
+use std::str::{self, FromStr};
+
+use crate::{graph::CSRGraph, io::graph::*};
+
+/// Same as [char::is_whitespace] for ASCII
+fn is_space(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r')
+}
+
+/// Remove `\n` or `\r\n` at the end of line as [str::lines]
+fn strip_line_ending(line: &[u8]) -> &[u8] {
+    match line.strip_suffix(b"\n") {
+        Some(line) => line.strip_suffix(b"\r").unwrap_or(line),
+        None => line,
+    }
+}
+
+/// Lines with 1-based line numbers, split as [str::lines]
+pub(crate) fn lines(input: &[u8]) -> impl Iterator<Item = (usize, &[u8])> {
+    input
+        .split_inclusive(|&b| b == b'\n')
+        .map(strip_line_ending)
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+}
+
+/// Decode a line, which is required to be UTF-8 as [FromMetisGraphFormat::from_metis_graph_reader]
+pub(crate) fn decode(line_number: usize, line: &[u8]) -> Result<&str, GraphFileError> {
+    str::from_utf8(line).map_err(|_| GraphFileError::InvalidEncoding {
+        line_number,
+        error: String::from_utf8(line.to_vec()).unwrap_err(),
+    })
+}
+
+/// Kind of a line in METIS graph file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LineKind {
+    Comment,
+    Blank,
+    Content,
+}
+
+impl LineKind {
+    pub(crate) fn of(line: &str) -> Self {
+        let line = line.trim_start();
+        if line.is_empty() {
+            LineKind::Blank
+        } else if line.starts_with('%') {
+            LineKind::Comment
+        } else {
+            LineKind::Content
+        }
+    }
+}
+
+/// Read the header skipping comments and blank lines,
+/// and returns it with the number of lines until the header and the rest of `input`
+pub(crate) fn split_header(input: &[u8]) -> Result<(Header, usize, &[u8]), GraphFileError> {
+    let mut begin = 0;
+    for (i, line) in input.split_inclusive(|&b| b == b'\n').enumerate() {
+        begin += line.len();
+        let line_number = i + 1;
+        let line = decode(line_number, strip_line_ending(line))?;
+        if LineKind::of(line) == LineKind::Content {
+            let header = Header::from_str(line)
+                .map_err(|error| GraphFileError::InvalidHeaderAt { line_number, error })?;
+            return Ok((header, line_number, &input[begin..]));
+        }
+    }
+    Err(GraphFileError::InvalidHeader(HeaderError::Empty))
+}
+
+/// The fast path cannot parse the line
+pub(crate) struct Fallback;
+
+/// Unsigned integer tokens separated by ASCII whitespace
+struct Tokens<'a> {
+    line: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(line: &'a [u8]) -> Self {
+        Tokens { line, pos: 0 }
+    }
+
+    /// Next token, or `None` at the end of line
+    fn next_int(&mut self) -> Result<Option<i32>, Fallback> {
+        while self.pos < self.line.len() && is_space(self.line[self.pos]) {
+            self.pos += 1;
+        }
+        if self.pos == self.line.len() {
+            return Ok(None);
+        }
+        let mut value: i64 = 0;
+        let begin = self.pos;
+        while let Some(&b) = self.line.get(self.pos) {
+            match b {
+                b'0'..=b'9' => {
+                    value = value * 10 + (b - b'0') as i64;
+                    if value > i32::MAX as i64 {
+                        return Err(Fallback);
+                    }
+                }
+                b if is_space(b) => break,
+                // sign, decimal point, non-ASCII, or invalid character
+                _ => return Err(Fallback),
+            }
+            self.pos += 1;
+        }
+        debug_assert!(self.pos > begin);
+        Ok(Some(value as i32))
+    }
+}
+
+/// Parse a vertex line and append it to `graph` without allocation
+///
+/// `graph` may be modified partially when this returns [Fallback].
+pub(crate) fn parse_line_fast(
+    header: &Header,
+    graph: &mut CSRGraph,
+    line: &[u8],
+) -> Result<(), Fallback> {
+    let mut tokens = Tokens::new(line);
+    if let Some(vsize) = graph.vertex_sizes.as_mut() {
+        vsize.push(tokens.next_int()?.ok_or(Fallback)?);
+    }
+    if let Some(vwgt) = graph.vertex_weights.as_mut() {
+        for _ in 0..header.num_weights {
+            vwgt.push(tokens.next_int()?.ok_or(Fallback)?);
+        }
+    }
+    while let Some(v) = tokens.next_int()? {
+        if v < 1 || v as usize > header.num_vertices {
+            return Err(Fallback);
+        }
+        // 1-based in file
+        graph.column_indices.push(v - 1);
+        if let Some(adjwgt) = graph.edge_weights.as_mut() {
+            adjwgt.push(tokens.next_int()?.ok_or(Fallback)?);
+        }
+    }
+    graph
+        .num_elements_in_row_cumsum
+        .push(graph.column_indices.len() as i32);
+    Ok(())
+}
+
+/// Lengths of the arrays of [CSRGraph] to roll back a partially parsed line
+struct Lengths([usize; 5]);
+
+impl Lengths {
+    fn of(graph: &CSRGraph) -> Self {
+        let len = |a: &Option<Vec<i32>>| a.as_ref().map_or(0, |a| a.len());
+        Lengths([
+            graph.column_indices.len(),
+            graph.num_elements_in_row_cumsum.len(),
+            len(&graph.vertex_weights),
+            len(&graph.vertex_sizes),
+            len(&graph.edge_weights),
+        ])
+    }
+
+    fn truncate(&self, graph: &mut CSRGraph) {
+        let [adjncy, xadj, vwgt, vsize, adjwgt] = self.0;
+        graph.column_indices.truncate(adjncy);
+        graph.num_elements_in_row_cumsum.truncate(xadj);
+        for (a, len) in [
+            (&mut graph.vertex_weights, vwgt),
+            (&mut graph.vertex_sizes, vsize),
+            (&mut graph.edge_weights, adjwgt),
+        ] {
+            if let Some(a) = a {
+                a.truncate(len);
+            }
+        }
+    }
+}
+
+/// Parse vertex lines following the header into `graph`
+///
+/// `num_read` is the number of vertices read before these lines, and is updated.
+pub(crate) fn parse_lines<'a>(
+    header: &Header,
+    graph: &mut CSRGraph,
+    num_read: &mut usize,
+    lines: impl Iterator<Item = (usize, &'a [u8])>,
+) -> Result<(), GraphFileError> {
+    for (line_number, line) in lines {
+        // Blank and comment lines in ASCII
+        match line.iter().position(|&b| !is_space(b)) {
+            None if *num_read >= header.num_vertices => continue,
+            Some(i) if line[i] == b'%' && line.is_ascii() => continue,
+            _ => {}
+        }
+
+        let lengths = Lengths::of(graph);
+        if parse_line_fast(header, graph, line).is_ok() {
+            *num_read += 1;
+            continue;
+        }
+        lengths.truncate(graph);
+
+        let line = decode(line_number, line)?;
+        match LineKind::of(line) {
+            LineKind::Comment => continue,
+            LineKind::Blank if *num_read >= header.num_vertices => continue,
+            _ => {}
+        }
+        *num_read += 1;
+        let line = Line::parse(header, *num_read as i32, line)
+            .map_err(|error| GraphFileError::InvalidLineAt { line_number, error })?;
+        graph.push_line(&line);
+    }
+    Ok(())
+}
+
+/// Lines of `body` numbered following `num_lines_before` lines
+fn lines_after(body: &[u8], num_lines_before: usize) -> impl Iterator<Item = (usize, &[u8])> {
+    lines(body).map(move |(i, line)| (num_lines_before + i, line))
+}
+
+/// Parse METIS graph file in memory
+pub(crate) fn parse_csr_graph(input: &[u8]) -> Result<CSRGraph, GraphFileError> {
+    let (header, num_header_lines, body) = split_header(input)?;
+    let mut graph = CSRGraph::new(&header);
+    graph.num_elements_in_row_cumsum.push(0);
+    parse_lines(
+        &header,
+        &mut graph,
+        &mut 0,
+        lines_after(body, num_header_lines),
+    )?;
+    graph.check_size(&header)?;
+    Ok(graph)
+}
+
+/// Default size of chunks parsed in parallel
+#[cfg(feature = "rayon")]
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Parse METIS graph file in memory, splitting the body into chunks parsed in parallel
+#[cfg(feature = "rayon")]
+pub(crate) fn parse_csr_graph_parallel(input: &[u8]) -> Result<CSRGraph, GraphFileError> {
+    parse_csr_graph_chunked(input, CHUNK_SIZE)
+}
+
+/// Split `body` into chunks of about `chunk_size` bytes at line boundaries
+#[cfg(feature = "rayon")]
+fn split_chunks(body: &[u8], chunk_size: usize) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut rest = body;
+    while !rest.is_empty() {
+        let end = match rest.get(chunk_size..) {
+            Some(tail) => match tail.iter().position(|&b| b == b'\n') {
+                Some(i) => chunk_size + i + 1,
+                None => rest.len(),
+            },
+            None => rest.len(),
+        };
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
+/// Vertices parsed from a chunk
+#[cfg(feature = "rayon")]
+struct Piece {
+    /// Graph of the vertices in the chunk, where `xadj` starts from 0
+    graph: CSRGraph,
+    num_vertices: usize,
+    num_lines: usize,
+}
+
+/// Parse a chunk into a [Piece], or `None` on any error
+#[cfg(feature = "rayon")]
+fn parse_piece(header: &Header, chunk: &[u8], ratio: f64) -> Option<Piece> {
+    // Preallocate for the share of the chunk in the body
+    let mut graph = CSRGraph::new(&Header {
+        num_vertices: (header.num_vertices as f64 * ratio) as usize + 1,
+        num_edges: (header.num_edges as f64 * ratio) as usize + 1,
+        ..header.clone()
+    });
+    graph.num_elements_in_row_cumsum.push(0);
+    let mut num_vertices = 0;
+    let mut num_lines = 0;
+    parse_lines(
+        header,
+        &mut graph,
+        &mut num_vertices,
+        lines(chunk).inspect(|_| num_lines += 1),
+    )
+    .ok()?;
+    Some(Piece {
+        graph,
+        num_vertices,
+        num_lines,
+    })
+}
+
+/// Append vertices in `piece` to `graph`
+#[cfg(feature = "rayon")]
+fn append(graph: &mut CSRGraph, piece: CSRGraph) {
+    let offset = graph.column_indices.len() as i32;
+    graph.num_elements_in_row_cumsum.extend(
+        piece.num_elements_in_row_cumsum[1..]
+            .iter()
+            .map(|x| x + offset),
+    );
+    graph.column_indices.extend(piece.column_indices);
+    for (a, b) in [
+        (&mut graph.vertex_weights, piece.vertex_weights),
+        (&mut graph.vertex_sizes, piece.vertex_sizes),
+        (&mut graph.edge_weights, piece.edge_weights),
+    ] {
+        if let (Some(a), Some(b)) = (a, b) {
+            a.extend(b);
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+fn parse_csr_graph_chunked(input: &[u8], chunk_size: usize) -> Result<CSRGraph, GraphFileError> {
+    use rayon::prelude::*;
+
+    let (header, num_header_lines, body) = split_header(input)?;
+    let chunks = split_chunks(body, chunk_size);
+    let pieces: Vec<Option<Piece>> = chunks
+        .par_iter()
+        .map(|chunk| parse_piece(&header, chunk, chunk.len() as f64 / body.len() as f64))
+        .collect();
+
+    let mut graph = CSRGraph::new(&header);
+    graph.num_elements_in_row_cumsum.push(0);
+    let mut num_read = 0;
+    let mut num_lines = num_header_lines;
+    let mut begin = 0;
+    for (chunk, piece) in chunks.iter().zip(pieces) {
+        match piece {
+            // Blank lines in the chunk are isolated vertices as long as the total does not exceed the header
+            Some(piece) if num_read + piece.num_vertices <= header.num_vertices => {
+                append(&mut graph, piece.graph);
+                num_read += piece.num_vertices;
+                num_lines += piece.num_lines;
+                begin += chunk.len();
+            }
+            // Otherwise parse the rest sequentially, which returns the same result or error as [parse_csr_graph]
+            _ => {
+                parse_lines(
+                    &header,
+                    &mut graph,
+                    &mut num_read,
+                    lines_after(&body[begin..], num_lines),
+                )?;
+                break;
+            }
+        }
+    }
+    graph.check_size(&header)?;
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Both parsers return the same graph or the same error
+    fn assert_same(input: &str) {
+        let expected = CSRGraph::from_metis_graph_str(input);
+        let actual = parse_csr_graph(input.as_bytes());
+        match (expected, actual) {
+            (Ok(expected), Ok(actual)) => assert_eq!(expected, actual, "{:?}", input),
+            (Err(expected), Err(actual)) => {
+                assert_eq!(expected.to_string(), actual.to_string(), "{:?}", input)
+            }
+            (expected, actual) => panic!("{:?}: {:?} != {:?}", input, expected, actual),
+        }
+    }
+
+    #[test]
+    fn examples() {
+        for input in [
+            examples::MANUAL_2A,
+            examples::MANUAL_2B,
+            examples::MANUAL_2C,
+            examples::MANUAL_2D,
+            examples::MANUAL_3A,
+        ] {
+            assert_same(input);
+        }
+        let graph = parse_csr_graph(examples::MANUAL_2D.as_bytes()).unwrap();
+        assert_eq!(graph.ncon(), 3);
+    }
+
+    #[test]
+    fn lines_as_str() {
+        for input in [
+            "",
+            "\n",
+            "a",
+            "a\n",
+            "a\n\nb",
+            "a\r\nb\n\n",
+            "\n\na",
+            "a\rb\r",
+        ] {
+            let expected: Vec<_> = input.lines().map(str::as_bytes).collect();
+            let actual: Vec<_> = lines(input.as_bytes()).map(|(_, line)| line).collect();
+            assert_eq!(expected, actual, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn fallback() {
+        // Comments, isolated vertices and CRLF
+        assert_same("% header\n\n3 2\n% vertex\n2\n1 3\r\n  % indented\n2\n\n \n");
+        assert_same("4 1\n3\n\n1\n\n");
+        // Decimal weights, signs and leading zeros
+        assert_same("3 2 011\n1.0 2 1\n+2 1 2 3 3.0\n1 002 4\n");
+        // Non-ASCII whitespace and comments
+        assert_same("3 2\n2\u{3000}\n1 3\n\u{3000}% コメント\n2\n");
+    }
+
+    #[test]
+    fn errors() {
+        assert_same("");
+        assert_same("% only comments\n");
+        assert_same("3 2\n2\n1 x\n2");
+        assert_same("3 2\n2\n1 4\n2");
+        assert_same("3 2\n2\n1 0\n2");
+        assert_same("3 2\n2\n1 99999999999\n2");
+        assert_same("3 2 001\n2 1\n1 1 3\n2 1");
+        assert_same("3 2 001\n2 1\n1 1 3 -1\n2 1");
+        assert_same("3 2 001\n2 1\n1 1 3 1.5\n2 1");
+        assert_same("3 2 010\n-1 2\n1 1 3\n1 2");
+        assert_same("3 2\n2\n1 3\n");
+        assert_same("3 3\n2\n1 3\n2");
+        assert_same("3 2\n2\n1 3\n2\n1");
+        assert_same("2 0 010 2\n1\n1\n");
+        assert_same("2 0 010\n\n1\n");
+        assert_same("% comment\n\n3 x\n2\n1 3\n2");
+
+        // Short vertex weights fall back to the str parser, and are rejected
+        let err = parse_csr_graph(b"2 0 010 2\n1\n1\n").unwrap_err();
+        assert!(matches!(
+            err,
+            GraphFileError::InvalidLineAt {
+                line_number: 2,
+                error: LineError::VertexWeightMissing {
+                    from_index: 1,
+                    ncon: 2,
+                    actual: 1
+                }
+            }
+        ));
+
+        let err = parse_csr_graph(b"3 2\n2\n1 3\xff\n2\n").unwrap_err();
+        assert!(matches!(
+            err,
+            GraphFileError::InvalidEncoding { line_number: 3, .. }
+        ));
+        let err = parse_csr_graph(b"3 2\n2\n1 x\n2\n% \xff").unwrap_err();
+        assert!(matches!(
+            err,
+            GraphFileError::InvalidLineAt { line_number: 3, .. }
+        ));
+    }
+
+    /// Parallel parser with small chunks returns the same graph or the same error
+    #[cfg(feature = "rayon")]
+    fn assert_same_parallel(input: &str) {
+        let expected = parse_csr_graph(input.as_bytes());
+        for chunk_size in [1, 2, 3, 5, 8, 13, 64] {
+            let actual = parse_csr_graph_chunked(input.as_bytes(), chunk_size);
+            match (&expected, actual) {
+                (Ok(expected), Ok(actual)) => assert_eq!(expected, &actual, "{:?}", input),
+                (Err(expected), Err(actual)) => {
+                    assert_eq!(expected.to_string(), actual.to_string(), "{:?}", input)
+                }
+                (expected, actual) => panic!("{:?}: {:?} != {:?}", input, expected, actual),
+            }
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel() {
+        for input in [
+            examples::MANUAL_2A,
+            examples::MANUAL_2B,
+            examples::MANUAL_2C,
+            examples::MANUAL_2D,
+            examples::MANUAL_3A,
+            "% header\n\n3 2\n% vertex\n2\n1 3\r\n  % indented\n2\n\n \n",
+            "4 1\n3\n\n1\n\n",
+            "4 1\n3\n\n1\n\n\n\n\n",
+            "3 2 011\n1.0 2 1\n+2 1 2 3 3.0\n1 002 4\n",
+            "3 2\n2\u{3000}\n1 3\n\u{3000}% コメント\n2\n",
+            "",
+            "3 2\n2\n1 x\n2",
+            "3 2 010\n1 2\n-1 1 3\n1 2",
+            "3 2\n2\n1 3\n",
+            "3 3\n2\n1 3\n2",
+            "3 2\n2\n1 3\n2\n1",
+            "3 2\n2\n1 3\n2\n\n1\n",
+        ] {
+            assert_same_parallel(input);
+        }
+    }
+
+    #[test]
+    fn metis_graph_file() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../metis-src/metis-5.1.0/graphs/4elt.graph"
+        );
+        let input = std::fs::read(path).unwrap();
+        let graph = CSRGraph::from_metis_graph(path).unwrap();
+        assert_eq!(parse_csr_graph(&input).unwrap(), graph);
+        #[cfg(feature = "rayon")]
+        assert_eq!(parse_csr_graph_chunked(&input, 1000).unwrap(), graph);
+        #[cfg(feature = "mmap")]
+        assert_eq!(
+            unsafe { CSRGraph::from_metis_graph_mmap(path) }.unwrap(),
+            graph
+        );
+    }
+}
@@ -0,0 +1,381 @@
+//! Transparent compression of METIS files
+//!
+//! Files compressed by gzip, zstd or bzip2 are detected by their magic bytes, or by the extension
+//! if the magic bytes are not recognized, and decompressed while reading.
+//! Files are compressed while writing according to the extension, e.g. `4elt.graph.gz`.
+//!
+//! Each format requires the corresponding `gzip`, `zstd` or `bzip2` cargo feature,
+//! and [io::ErrorKind::Unsupported] error is returned for a disabled format.
+
+use std::{
+    borrow::Cow,
+    fs,
+    io::{self, BufRead, Read, Write},
+    path::*,
+};
+
+/// Compression format of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Not compressed
+    Plain,
+    /// `.gz`
+    Gzip,
+    /// `.zst`
+    Zstd,
+    /// `.bz2`
+    Bzip2,
+}
+
+impl Compression {
+    /// Detect from the first bytes of a file
+    pub fn from_magic(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else if bytes.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else {
+            Compression::Plain
+        }
+    }
+
+    /// Detect from the extension of the path
+    pub fn from_extension(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            Some("bz2") => Compression::Bzip2,
+            _ => Compression::Plain,
+        }
+    }
+
+    /// Check the cargo feature for this format is enabled
+    fn check_enabled(self) -> io::Result<()> {
+        let (enabled, feature) = match self {
+            Compression::Plain => return Ok(()),
+            Compression::Gzip => (cfg!(feature = "gzip"), "gzip"),
+            Compression::Zstd => (cfg!(feature = "zstd"), "zstd"),
+            Compression::Bzip2 => (cfg!(feature = "bzip2"), "bzip2"),
+        };
+        if enabled {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{:?} compression requires `{}` feature", self, feature),
+            ))
+        }
+    }
+}
+
+/// Open a file for reading, and decompress it if compressed
+pub fn open(path: impl AsRef<Path>) -> io::Result<Box<dyn BufRead>> {
+    let path = path.as_ref();
+    let mut reader = io::BufReader::new(fs::File::open(path)?);
+    let compression = match Compression::from_magic(reader.fill_buf()?) {
+        Compression::Plain => Compression::from_extension(path),
+        compression => compression,
+    };
+    decoder(reader, compression)
+}
+
+/// Decompress `reader` if it starts with magic bytes of a compression format, e.g. for stdin
+pub fn decompress<'a>(mut reader: impl BufRead + 'a) -> io::Result<Box<dyn BufRead + 'a>> {
+    let compression = Compression::from_magic(reader.fill_buf()?);
+    decoder(reader, compression)
+}
+
+/// Decompress `input` in memory if it starts with magic bytes of a compression format
+pub fn decompress_bytes(input: &[u8]) -> io::Result<Cow<'_, [u8]>> {
+    match Compression::from_magic(input) {
+        Compression::Plain => Ok(Cow::Borrowed(input)),
+        compression => {
+            let mut buf = Vec::new();
+            decoder(input, compression)?.read_to_end(&mut buf)?;
+            Ok(Cow::Owned(buf))
+        }
+    }
+}
+
+fn decoder<'a>(
+    reader: impl BufRead + 'a,
+    compression: Compression,
+) -> io::Result<Box<dyn BufRead + 'a>> {
+    compression.check_enabled()?;
+    Ok(match compression {
+        Compression::Plain => Box::new(reader),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Box::new(io::BufReader::new(flate2::bufread::MultiGzDecoder::new(
+            reader,
+        ))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Box::new(io::BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Box::new(io::BufReader::new(bzip2::bufread::MultiBzDecoder::new(
+            reader,
+        ))),
+        #[allow(unreachable_patterns)]
+        _ => unreachable!("Disabled format is rejected above"),
+    })
+}
+
+/// Create a file for writing, and compress it according to the extension
+///
+/// The returned writer should be finished by [Writer::finish] to complete the compressed stream
+/// and to get its errors.
+pub fn create(path: impl AsRef<Path>) -> io::Result<Writer> {
+    let path = path.as_ref();
+    let compression = Compression::from_extension(path);
+    compression.check_enabled()?;
+    let f = io::BufWriter::new(fs::File::create(path)?);
+    let inner = match compression {
+        Compression::Plain => Inner::Plain(f),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Inner::Gzip(flate2::write::GzEncoder::new(
+            f,
+            flate2::Compression::default(),
+        )),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Inner::Zstd(zstd::Encoder::new(f, 0)?),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Inner::Bzip2(bzip2::write::BzEncoder::new(
+            f,
+            bzip2::Compression::default(),
+        )),
+        #[allow(unreachable_patterns)]
+        _ => unreachable!("Disabled format is rejected above"),
+    };
+    Ok(Writer { inner: Some(inner) })
+}
+
+/// File writer created by [create]
+///
+/// Dropping the writer without [Writer::finish] finishes the stream on a best-effort basis,
+/// and errors are ignored.
+pub struct Writer {
+    /// `None` after finished
+    inner: Option<Inner>,
+}
+
+enum Inner {
+    Plain(io::BufWriter<fs::File>),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<io::BufWriter<fs::File>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, io::BufWriter<fs::File>>),
+    #[cfg(feature = "bzip2")]
+    Bzip2(bzip2::write::BzEncoder<io::BufWriter<fs::File>>),
+}
+
+impl Inner {
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Inner::Plain(mut w) => w.flush(),
+            #[cfg(feature = "gzip")]
+            Inner::Gzip(w) => w.finish()?.flush(),
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(w) => w.finish()?.flush(),
+            #[cfg(feature = "bzip2")]
+            Inner::Bzip2(w) => w.finish()?.flush(),
+        }
+    }
+}
+
+impl Writer {
+    /// Finish the compressed stream, and flush it into the file
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.inner.take() {
+            Some(inner) => inner.finish(),
+            None => Ok(()),
+        }
+    }
+
+    fn get_mut(&mut self) -> &mut dyn Write {
+        match self.inner.as_mut().expect("Writer is used after finished") {
+            Inner::Plain(w) => w,
+            #[cfg(feature = "gzip")]
+            Inner::Gzip(w) => w,
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(w) => w,
+            #[cfg(feature = "bzip2")]
+            Inner::Bzip2(w) => w,
+        }
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            let _ = inner.finish();
+        }
+    }
+}
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.get_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.get_mut().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::CSRGraph,
+        io::{
+            graph::*,
+            mesh::{examples as mesh_examples, FromMetisMeshFormat},
+        },
+        mesh::Mesh,
+    };
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("metis-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn detect() {
+        assert_eq!(Compression::from_magic(b"\x1f\x8b\x08"), Compression::Gzip);
+        assert_eq!(
+            Compression::from_magic(b"\x28\xb5\x2f\xfd"),
+            Compression::Zstd
+        );
+        assert_eq!(Compression::from_magic(b"BZh9"), Compression::Bzip2);
+        assert_eq!(Compression::from_magic(b"15 22\n"), Compression::Plain);
+        assert_eq!(Compression::from_magic(b""), Compression::Plain);
+
+        assert_eq!(
+            Compression::from_extension("4elt.graph.gz"),
+            Compression::Gzip
+        );
+        assert_eq!(
+            Compression::from_extension("4elt.graph.zst"),
+            Compression::Zstd
+        );
+        assert_eq!(
+            Compression::from_extension("4elt.graph.bz2"),
+            Compression::Bzip2
+        );
+        assert_eq!(
+            Compression::from_extension("4elt.graph"),
+            Compression::Plain
+        );
+    }
+
+    /// Write mesh file by [create], and read it back
+    fn mesh_roundtrip(path: &Path) {
+        let mut w = create(path).unwrap();
+        w.write_all(mesh_examples::TRIANGLES.trim().as_bytes())
+            .unwrap();
+        w.finish().unwrap();
+        let mesh = Mesh::from_metis_mesh(path).unwrap();
+        let expected = Mesh::from_metis_mesh_str(mesh_examples::TRIANGLES).unwrap();
+        assert_eq!(mesh.eptr(), expected.eptr());
+        assert_eq!(mesh.eind(), expected.eind());
+    }
+
+    #[test]
+    fn plain() {
+        let dir = temp_dir("plain");
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let path = dir.join("grid.graph");
+        graph.to_metis_graph(&path).unwrap();
+        assert_eq!(CSRGraph::from_metis_graph(&path).unwrap(), graph);
+
+        let input = decompress(examples::MANUAL_3A.as_bytes()).unwrap();
+        assert_eq!(CSRGraph::from_metis_graph_reader(input).unwrap(), graph);
+
+        mesh_roundtrip(&dir.join("triangles.mesh"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn disabled() {
+        let dir = temp_dir("disabled");
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let path = dir.join("grid.graph.gz");
+        let err = graph.to_metis_graph(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert!(!path.exists());
+
+        // Detected by magic bytes even without extension
+        let path = dir.join("grid.graph");
+        fs::write(&path, b"\x1f\x8b\x08\x00").unwrap();
+        assert!(matches!(
+            CSRGraph::from_metis_graph(&path).unwrap_err(),
+            GraphFileError::IO(e) if e.kind() == io::ErrorKind::Unsupported
+        ));
+        assert!(matches!(
+            CSRGraph::from_metis_graph_bytes(&fs::read(&path).unwrap()).unwrap_err(),
+            GraphFileError::IO(e) if e.kind() == io::ErrorKind::Unsupported
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Write graph and mesh compressed by the extension, and read them back
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2"))]
+    fn roundtrip(extension: &str) {
+        let dir = temp_dir(extension);
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_2C).unwrap();
+        let path = dir.join(format!("graph.{}", extension));
+        graph.to_metis_graph(&path).unwrap();
+        assert_ne!(
+            Compression::from_magic(&fs::read(&path).unwrap()),
+            Compression::Plain
+        );
+        assert_eq!(CSRGraph::from_metis_graph(&path).unwrap(), graph);
+
+        // Byte-level parser decompresses in memory
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(CSRGraph::from_metis_graph_bytes(&bytes).unwrap(), graph);
+        #[cfg(feature = "mmap")]
+        assert_eq!(
+            unsafe { CSRGraph::from_metis_graph_mmap(&path) }.unwrap(),
+            graph
+        );
+
+        // Detected by magic bytes without extension
+        let renamed = dir.join("graph");
+        fs::rename(&path, &renamed).unwrap();
+        assert_eq!(CSRGraph::from_metis_graph(&renamed).unwrap(), graph);
+        let input = decompress(io::BufReader::new(fs::File::open(&renamed).unwrap())).unwrap();
+        assert_eq!(CSRGraph::from_metis_graph_reader(input).unwrap(), graph);
+
+        mesh_roundtrip(&dir.join(format!("mesh.{}", extension)));
+
+        // Dropping the writer finishes the stream
+        let path = dir.join(format!("dropped.{}", extension));
+        let mut w = create(&path).unwrap();
+        w.write_all(examples::MANUAL_2C.as_bytes()).unwrap();
+        drop(w);
+        assert_eq!(CSRGraph::from_metis_graph(&path).unwrap(), graph);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip() {
+        roundtrip("gz");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        roundtrip("zst");
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn bzip2() {
+        roundtrip("bz2");
+    }
+}
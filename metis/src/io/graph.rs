@@ -1,12 +1,15 @@
 //! I/O for METIS Graph formats
 
 use std::{
-    fs,
-    io::{self, BufRead},
+    cell::Cell,
+    fmt,
+    io::{self, BufRead, Write},
     path::*,
     str::FromStr,
 };
 
+use super::compress;
+
 /// Example graphs
 pub mod examples {
     /// graph in Figure 2 (a) of the manual
@@ -60,59 +63,218 @@ pub mod examples {
     /// graph in Figure 3 (a) of the manual
     pub const MANUAL_3A: &str = r#"
         15 22
-        1 5
-        0 2 6
+        2 6
         1 3 7
         2 4 8
-        3 9
-        0 6 10
-        1 5 7 11
+        3 5 9
+        4 10
+        1 7 11
         2 6 8 12
         3 7 9 13
-        4 8 14
-        5 11
-        6 10 12
+        4 8 10 14
+        5 9 15
+        6 12
         7 11 13
         8 12 14
-        9 13
+        9 13 15
+        10 14
     "#;
 }
 
+/// Visitor receiving the contents of METIS graph file while it is read
+///
+/// [GraphVisitor::vertex] is called for each vertex line in order, followed by [GraphVisitor::edge]
+/// for each of its neighbours. Vertex indices are 1-based as in the file,
+/// and each undirected edge is visited from both ends.
+/// Only the current line is kept in memory, see [visit_metis_graph].
+pub trait GraphVisitor {
+    type Output;
+
+    /// Called with the header before any vertex
+    fn begin(&mut self, _header: &Header) -> Result<(), GraphFileError> {
+        Ok(())
+    }
+
+    /// Called for each vertex line
+    fn vertex(&mut self, _line: &Line) -> Result<(), GraphFileError> {
+        Ok(())
+    }
+
+    /// Called for the edge from `from` to `to` with its weight if the file has edge weights
+    fn edge(&mut self, _from: i32, _to: i32, _weight: Option<i32>) -> Result<(), GraphFileError> {
+        Ok(())
+    }
+
+    /// Called after the last vertex
+    fn end(self) -> Result<Self::Output, GraphFileError>;
+}
+
+/// Visit vertices and edges of parsed lines
+pub fn visit_metis_graph_iter<V: GraphVisitor>(
+    header: &Header,
+    lines: impl Iterator<Item = Result<Line, LineError>>,
+    mut visitor: V,
+) -> Result<V::Output, GraphFileError> {
+    visitor.begin(header)?;
+    for line in lines {
+        let line = line?;
+        visitor.vertex(&line)?;
+        for (i, &to_index) in line.vertices.iter().enumerate() {
+            let weight = line.edge_weights.as_ref().map(|ws| ws[i]);
+            visitor.edge(line.from_index, to_index, weight)?;
+        }
+    }
+    visitor.end()
+}
+
+/// Visit METIS graph file as a string
+pub fn visit_metis_graph_str<V: GraphVisitor>(
+    input: &str,
+    visitor: V,
+) -> Result<V::Output, GraphFileError> {
+    visit_metis_graph_lines(input.lines().map(|line| Ok(line.to_string())), visitor)
+}
+
+/// Visit METIS graph file, which is decompressed if compressed (see [super::compress])
+pub fn visit_metis_graph<V: GraphVisitor>(
+    path: impl AsRef<Path>,
+    visitor: V,
+) -> Result<V::Output, GraphFileError> {
+    visit_metis_graph_reader(compress::open(path)?, visitor)
+}
+
+/// Visit METIS graph file from a reader
+pub fn visit_metis_graph_reader<V: GraphVisitor>(
+    reader: impl BufRead,
+    visitor: V,
+) -> Result<V::Output, GraphFileError> {
+    visit_metis_graph_lines(read_lines(reader), visitor)
+}
+
+fn visit_metis_graph_lines<V: GraphVisitor>(
+    lines: impl Iterator<Item = Result<String, GraphFileError>>,
+    visitor: V,
+) -> Result<V::Output, GraphFileError> {
+    // The first error in reading lines stops the iteration, and is reported prior to parse errors
+    let read_error = Cell::new(None);
+
+    // Lines starting with `%` are comments, and line numbers are counted from 1 in the file
+    let mut lines = lines
+        .map_while(|line| line.map_err(|e| read_error.set(Some(e))).ok())
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !line.trim_start().starts_with('%'));
+
+    // Blank lines before the header are skipped
+    let (line_number, header) = match lines.by_ref().find(|(_, line)| !line.trim().is_empty()) {
+        Some(header) => header,
+        None => {
+            return Err(read_error
+                .take()
+                .unwrap_or(GraphFileError::InvalidHeader(HeaderError::Empty)))
+        }
+    };
+    let header = Header::from_str(&header)
+        .map_err(|error| GraphFileError::InvalidHeaderAt { line_number, error })?;
+
+    // Empty lines are isolated vertices, and blank lines after the last vertex are ignored
+    let mut num_read = 0;
+    let mut error_line_number = None;
+    let lines = lines.filter_map(|(line_number, line)| {
+        if num_read >= header.num_vertices && line.trim().is_empty() {
+            return None;
+        }
+        num_read += 1;
+        let line = Line::parse(&header, num_read as i32, &line);
+        if line.is_err() && error_line_number.is_none() {
+            error_line_number = Some(line_number);
+        }
+        Some(line)
+    });
+    let result = visit_metis_graph_iter(&header, lines, visitor);
+    if let Some(e) = read_error.take() {
+        return Err(e);
+    }
+    match result {
+        Err(GraphFileError::InvalidLine(error)) => match error_line_number {
+            Some(line_number) => Err(GraphFileError::InvalidLineAt { line_number, error }),
+            None => Err(GraphFileError::InvalidLine(error)),
+        },
+        result => result,
+    }
+}
+
 /// Constructable from METIS Graph format
 pub trait FromMetisGraphFormat: Sized {
+    /// Visitor constructing `Self`
+    type Visitor: GraphVisitor<Output = Self> + Default;
+
     fn from_metis_graph_iter(
         header: &Header,
         lines: impl Iterator<Item = Result<Line, LineError>>,
-    ) -> Result<Self, GraphFileError>;
+    ) -> Result<Self, GraphFileError> {
+        visit_metis_graph_iter(header, lines, Self::Visitor::default())
+    }
 
     /// Read METIS graph file as a string (assumes to be small)
     fn from_metis_graph_str(input: &str) -> Result<Self, GraphFileError> {
-        // This default impl assumes `input` is not too large
-        Self::from_metis_graph_lines(input.trim().lines().map(|line| line.to_string()))
+        visit_metis_graph_str(input, Self::Visitor::default())
     }
 
-    /// Read METIS graph file
+    /// Read METIS graph file, which is decompressed if compressed (see [super::compress])
     fn from_metis_graph(path: impl AsRef<Path>) -> Result<Self, GraphFileError> {
-        let mut f = fs::File::open(path.as_ref())?;
-        let buf = io::BufReader::new(&mut f);
-        Self::from_metis_graph_lines(buf.lines().map(|line| line.unwrap()))
+        visit_metis_graph(path, Self::Visitor::default())
     }
 
-    // common default implementations
-    #[doc(hidden)]
-    fn from_metis_graph_lines(
-        mut lines: impl Iterator<Item = String>,
-    ) -> Result<Self, GraphFileError> {
-        let header = Header::from_str(
-            &lines
-                .next()
-                .ok_or(GraphFileError::InvalidHeader(HeaderError::Empty))?,
-        )?;
-        let lines = lines.enumerate().map(|(from_index, line)| {
-            let from_index = from_index as i32 + 1;
-            Line::parse(&header, from_index, &line)
-        });
-        Self::from_metis_graph_iter(&header, lines)
+    /// Read METIS graph file from a reader, e.g. a file, stdin or an in-memory buffer
+    fn from_metis_graph_reader(reader: impl BufRead) -> Result<Self, GraphFileError> {
+        visit_metis_graph_reader(reader, Self::Visitor::default())
+    }
+}
+
+/// Lines of `reader` without line endings as [BufRead::lines],
+/// but invalid UTF-8 is reported with the line number
+fn read_lines(mut reader: impl BufRead) -> impl Iterator<Item = Result<String, GraphFileError>> {
+    let mut line_number = 0;
+    std::iter::from_fn(move || {
+        let mut buf = Vec::new();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(e) => return Some(Err(e.into())),
+        }
+        line_number += 1;
+        if buf.ends_with(b"\n") {
+            buf.pop();
+            if buf.ends_with(b"\r") {
+                buf.pop();
+            }
+        }
+        Some(
+            String::from_utf8(buf)
+                .map_err(|error| GraphFileError::InvalidEncoding { line_number, error }),
+        )
+    })
+}
+
+/// Writable in METIS Graph format
+pub trait ToMetisGraphFormat {
+    /// Write header and adjacency list of each vertex
+    fn write_metis_graph(&self, w: impl Write) -> io::Result<()>;
+
+    /// Write METIS graph file into a string (assumes to be small)
+    fn to_metis_graph_string(&self) -> String {
+        let mut buf = Vec::new();
+        self.write_metis_graph(&mut buf)
+            .expect("Writing to Vec never fails");
+        String::from_utf8(buf).expect("METIS graph format is ASCII")
+    }
+
+    /// Write METIS graph file, which is compressed according to the extension (see [super::compress])
+    fn to_metis_graph(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut w = compress::create(path)?;
+        self.write_metis_graph(&mut w)?;
+        w.finish()
     }
 }
 
@@ -122,17 +284,44 @@ pub enum LineError {
     #[error("vertex size `s` in manual is missing")]
     VertexSizeMissing,
 
+    #[error("Vertex {from_index} must have {ncon} weights, but has {actual}")]
+    VertexWeightMissing {
+        from_index: i32,
+        ncon: usize,
+        actual: usize,
+    },
+
     #[error("edge weight does not exists")]
     EdgeWeightMissing,
 
     #[error("Vertex is out-of-range: {index} > {num_vertices}")]
     VertexOutOfRange { index: i32, num_vertices: i32 },
 
-    #[error(transparent)]
-    ParseIntError(#[from] std::num::ParseIntError),
+    #[error("Vertex index must be positive since it starts from 1: {index}")]
+    NonPositiveVertexIndex { index: i32 },
+
+    #[error("Weight of vertex {from_index} must be non-negative: {weight}")]
+    NegativeVertexWeight { from_index: i32, weight: i32 },
+
+    #[error("Weight of edge ({from_index}, {to_index}) must be non-negative: {weight}")]
+    NegativeEdgeWeight {
+        from_index: i32,
+        to_index: i32,
+        weight: i32,
+    },
+
+    #[error("Weight of vertex {from_index} must be an integer: {weight}")]
+    NonIntegralVertexWeight { from_index: i32, weight: f64 },
+
+    #[error("Weight of edge ({from_index}, {to_index}) must be an integer: {weight}")]
+    NonIntegralEdgeWeight {
+        from_index: i32,
+        to_index: i32,
+        weight: f64,
+    },
 
     #[error(transparent)]
-    ParseFloatError(#[from] std::num::ParseFloatError),
+    ParseIntError(#[from] std::num::ParseIntError),
 }
 
 /// Error for invalid header line
@@ -147,6 +336,11 @@ pub enum HeaderError {
     #[error("Format spec in header is invalid: {fmt}")]
     InvalidFormat { fmt: String },
 
+    #[error(
+        "Number of vertex weights is specified, but the format spec does not have vertex weights"
+    )]
+    UnexpectedNumWeights { ncon: usize },
+
     #[error(transparent)]
     ParseIntError(#[from] std::num::ParseIntError),
 }
@@ -157,9 +351,30 @@ pub enum GraphFileError {
     #[error(transparent)]
     InvalidHeader(#[from] HeaderError),
 
+    #[error("Invalid header at line {line_number}: {error}")]
+    InvalidHeaderAt {
+        line_number: usize,
+        #[source]
+        error: HeaderError,
+    },
+
     #[error(transparent)]
     InvalidLine(#[from] LineError),
 
+    #[error("Invalid line {line_number}: {error}")]
+    InvalidLineAt {
+        line_number: usize,
+        #[source]
+        error: LineError,
+    },
+
+    #[error("Line {line_number} is not valid UTF-8")]
+    InvalidEncoding {
+        line_number: usize,
+        #[source]
+        error: std::string::FromUtf8Error,
+    },
+
     #[error("Vertex size mismatch: actual({actual}) != header({header})")]
     VertexSizeMissing { actual: usize, header: usize },
 
@@ -171,7 +386,7 @@ pub enum GraphFileError {
 }
 
 /// Graph file format specification in the header
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct Format {
     pub has_vertex_size: bool,
     pub has_vertex_weight: bool,
@@ -180,8 +395,9 @@ pub struct Format {
 
 impl FromStr for Format {
     type Err = HeaderError;
+    /// Parse as a number as METIS does, i.e. `1` is `001` and `11` is `011`
     fn from_str(fmt: &str) -> Result<Self, HeaderError> {
-        if fmt.len() != 3 {
+        if fmt.is_empty() || fmt.len() > 3 {
             return Err(HeaderError::InvalidFormat { fmt: fmt.into() });
         }
 
@@ -190,7 +406,8 @@ impl FromStr for Format {
             b'0' => Ok(false),
             _ => Err(HeaderError::InvalidFormat { fmt: fmt.into() }),
         };
-        let bytes = fmt.as_bytes();
+        let padded = format!("{:0>3}", fmt);
+        let bytes = padded.as_bytes();
         Ok(Format {
             has_vertex_size: byte2bool(bytes[0])?,
             has_vertex_weight: byte2bool(bytes[1])?,
@@ -199,13 +416,17 @@ impl FromStr for Format {
     }
 }
 
-impl Default for Format {
-    fn default() -> Self {
-        Format {
-            has_edge_weight: false,
-            has_vertex_weight: false,
-            has_vertex_size: false,
-        }
+impl fmt::Display for Format {
+    /// Three digits, e.g. `011`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bit = |b| if b { '1' } else { '0' };
+        write!(
+            f,
+            "{}{}{}",
+            bit(self.has_vertex_size),
+            bit(self.has_vertex_weight),
+            bit(self.has_edge_weight)
+        )
     }
 }
 
@@ -217,14 +438,15 @@ pub struct Header {
     /// Number of edges
     pub num_edges: usize,
     pub fmt: Format,
-    /// Number of vertex weights associated with each vertex of the graph
+    /// Number of vertex weights associated with each vertex of the graph, `ncon` in manual.
+    /// One if `ncon` is omitted or zero.
     pub num_weights: usize,
 }
 
 impl FromStr for Header {
     type Err = HeaderError;
     fn from_str(line: &str) -> Result<Self, HeaderError> {
-        let mut split_iter = line.trim().split_whitespace();
+        let mut split_iter = line.split_whitespace();
         let num_vertices = split_iter.next().ok_or(HeaderError::Empty)?.parse()?;
         let num_edges = split_iter
             .next()
@@ -234,21 +456,38 @@ impl FromStr for Header {
             Some(fmt) => Format::from_str(fmt)?,
             None => Format::default(),
         };
-        // If this parameter is omitted,
+        // If this parameter is omitted or zero,
         // then the vertices of the graph are assumed to have a single weight
         let num_weights = match split_iter.next() {
             Some(ncon) => ncon.parse()?,
-            None => 1,
+            None => 0,
         };
+        if num_weights > 0 && !fmt.has_vertex_weight {
+            return Err(HeaderError::UnexpectedNumWeights { ncon: num_weights });
+        }
         Ok(Header {
             num_vertices,
             num_edges,
             fmt,
-            num_weights,
+            num_weights: num_weights.max(1),
         })
     }
 }
 
+impl fmt::Display for Header {
+    /// Header line with the minimal `fmt` and `ncon` fields
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.num_vertices, self.num_edges)?;
+        if self.fmt != Format::default() {
+            write!(f, " {}", self.fmt)?;
+            if self.fmt.has_vertex_weight && self.num_weights > 1 {
+                write!(f, " {}", self.num_weights)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Parsed line in METIS graph format
 #[derive(Debug)]
 pub struct Line {
@@ -259,17 +498,41 @@ pub struct Line {
     pub vertex_size: Option<i32>,
     /// `w_1`, `w_2`, ... in manual
     /// None if Header.has_vertex_weight is false
-    pub vertex_weights: Option<Vec<f32>>,
+    pub vertex_weights: Option<Vec<i32>>,
     /// `v1`, ... in manual
     pub vertices: Vec<i32>,
     /// `e1`, ... in manual
     /// None if Header.has_edge_weight is false
-    pub edge_weights: Option<Vec<f32>>,
+    pub edge_weights: Option<Vec<i32>>,
+}
+
+/// Weight token which is not a valid integer
+enum WeightError {
+    Negative(i32),
+    NonIntegral(f64),
+    Parse(std::num::ParseIntError),
+}
+
+/// Parse non-negative integer weight.
+/// Integral values written as a decimal, e.g. `3.0`, are also accepted.
+fn parse_weight(token: &str) -> Result<i32, WeightError> {
+    let w = match token.parse::<i32>() {
+        Ok(w) => w,
+        Err(e) => match token.parse::<f64>() {
+            Ok(w) if w.fract() != 0.0 || !w.is_finite() => return Err(WeightError::NonIntegral(w)),
+            Ok(w) if w >= i32::MIN as f64 && w <= i32::MAX as f64 => w as i32,
+            _ => return Err(WeightError::Parse(e)),
+        },
+    };
+    if w < 0 {
+        return Err(WeightError::Negative(w));
+    }
+    Ok(w)
 }
 
 impl Line {
     pub fn parse(header: &Header, from_index: i32, line: &str) -> Result<Self, LineError> {
-        let mut nums = line.trim().split_whitespace();
+        let mut nums = line.split_whitespace();
         let vertex_size = if header.fmt.has_vertex_size {
             let s = nums.next().ok_or(LineError::VertexSizeMissing)?;
             let s: i32 = s.parse()?;
@@ -281,8 +544,25 @@ impl Line {
             let ws = nums
                 .by_ref()
                 .take(header.num_weights)
-                .map(|num| num.parse())
-                .collect::<Result<Vec<f32>, _>>()?;
+                .map(|num| {
+                    parse_weight(num).map_err(|e| match e {
+                        WeightError::Negative(weight) => {
+                            LineError::NegativeVertexWeight { from_index, weight }
+                        }
+                        WeightError::NonIntegral(weight) => {
+                            LineError::NonIntegralVertexWeight { from_index, weight }
+                        }
+                        WeightError::Parse(e) => e.into(),
+                    })
+                })
+                .collect::<Result<Vec<i32>, _>>()?;
+            if ws.len() < header.num_weights {
+                return Err(LineError::VertexWeightMissing {
+                    from_index,
+                    ncon: header.num_weights,
+                    actual: ws.len(),
+                });
+            }
             Some(ws)
         } else {
             None
@@ -291,8 +571,22 @@ impl Line {
             let mut vs = Vec::new();
             let mut es = Vec::new();
             while let Some(v) = nums.next() {
-                vs.push(v.parse()?);
-                let e = nums.next().ok_or(LineError::EdgeWeightMissing)?.parse()?;
+                let to_index = v.parse()?;
+                vs.push(to_index);
+                let e = nums.next().ok_or(LineError::EdgeWeightMissing)?;
+                let e = parse_weight(e).map_err(|e| match e {
+                    WeightError::Negative(weight) => LineError::NegativeEdgeWeight {
+                        from_index,
+                        to_index,
+                        weight,
+                    },
+                    WeightError::NonIntegral(weight) => LineError::NonIntegralEdgeWeight {
+                        from_index,
+                        to_index,
+                        weight,
+                    },
+                    WeightError::Parse(e) => e.into(),
+                })?;
                 es.push(e);
             }
             (vs, Some(es))
@@ -301,6 +595,9 @@ impl Line {
             (vs, None)
         };
         for &index in &vertices {
+            if index < 1 {
+                return Err(LineError::NonPositiveVertexIndex { index });
+            }
             let num_vertices = header.num_vertices as i32;
             if index > num_vertices {
                 return Err(LineError::VertexOutOfRange {
@@ -322,6 +619,62 @@ impl Line {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    /// Number of vertices of each degree, and the total edge weight
+    #[derive(Default)]
+    struct DegreeHistogram {
+        histogram: Vec<usize>,
+        total_edge_weight: i64,
+    }
+
+    impl GraphVisitor for DegreeHistogram {
+        type Output = (Vec<usize>, i64);
+
+        fn vertex(&mut self, line: &Line) -> Result<(), GraphFileError> {
+            let degree = line.vertices.len();
+            if self.histogram.len() <= degree {
+                self.histogram.resize(degree + 1, 0);
+            }
+            self.histogram[degree] += 1;
+            Ok(())
+        }
+
+        fn edge(
+            &mut self,
+            _from: i32,
+            _to: i32,
+            weight: Option<i32>,
+        ) -> Result<(), GraphFileError> {
+            self.total_edge_weight += weight.unwrap_or(1) as i64;
+            Ok(())
+        }
+
+        fn end(self) -> Result<Self::Output, GraphFileError> {
+            Ok((self.histogram, self.total_edge_weight))
+        }
+    }
+
+    #[test]
+    fn visitor() {
+        let (histogram, total) =
+            visit_metis_graph_str(examples::MANUAL_3A, DegreeHistogram::default()).unwrap();
+        // 5x3 grid: 4 corners, 8 other boundary vertices, and 3 interior vertices
+        assert_eq!(histogram, vec![0, 0, 4, 8, 3]);
+        assert_eq!(total, 44);
+
+        let (_, total) =
+            visit_metis_graph_str(examples::MANUAL_2B, DegreeHistogram::default()).unwrap();
+        // Each edge is visited from both ends
+        assert_eq!(total, 2 * 27);
+
+        // Errors are reported with the line number as constructing graphs
+        let err = visit_metis_graph_str("2 1\n2\nx\n", DegreeHistogram::default()).unwrap_err();
+        assert!(matches!(
+            err,
+            GraphFileError::InvalidLineAt { line_number: 3, .. }
+        ));
+    }
+
     mod format {
         use super::*;
         #[test]
@@ -347,7 +700,31 @@ mod tests {
         #[should_panic]
         #[test]
         fn new_invalid3() {
-            let _fmt = Format::from_str("01").unwrap();
+            let _fmt = Format::from_str("1a").unwrap();
+        }
+
+        #[test]
+        fn short() {
+            assert_eq!(
+                Format::from_str("1").unwrap(),
+                Format::from_str("001").unwrap()
+            );
+            assert_eq!(
+                Format::from_str("11").unwrap(),
+                Format::from_str("011").unwrap()
+            );
+            assert_eq!(
+                Format::from_str("10").unwrap(),
+                Format::from_str("010").unwrap()
+            );
+            assert_eq!(Format::from_str("0").unwrap(), Format::default());
+            assert!(Format::from_str("").is_err());
+        }
+
+        #[test]
+        fn display() {
+            assert_eq!(Format::from_str("1").unwrap().to_string(), "001");
+            assert_eq!(Format::from_str("110").unwrap().to_string(), "110");
         }
     }
 
@@ -386,11 +763,55 @@ mod tests {
         fn parse_fail_negative() {
             let _ = Header::from_str("10 -34").unwrap();
         }
+
+        #[test]
+        fn num_weights() {
+            // zero is regarded as a single weight as METIS does
+            let header = Header::from_str("10 34 10 0").unwrap();
+            assert_eq!(header.num_weights, 1);
+
+            assert_eq!(
+                Header::from_str("10 34 1 3").unwrap_err(),
+                HeaderError::UnexpectedNumWeights { ncon: 3 }
+            );
+            // ncon without vertex weights is allowed if it is zero
+            assert_eq!(Header::from_str("10 34 1 0").unwrap().num_weights, 1);
+        }
+
+        #[test]
+        fn display() {
+            for line in ["10 34", "10 34 001", "10 34 011", "10 34 110 3"] {
+                assert_eq!(Header::from_str(line).unwrap().to_string(), line);
+            }
+            // minimal fields
+            assert_eq!(Header::from_str("10 34 000").unwrap().to_string(), "10 34");
+            assert_eq!(
+                Header::from_str("10 34 1").unwrap().to_string(),
+                "10 34 001"
+            );
+            assert_eq!(
+                Header::from_str("10 34 11 1").unwrap().to_string(),
+                "10 34 011"
+            );
+        }
     }
 
     mod line {
         use super::*;
 
+        #[test]
+        fn non_positive_index() {
+            let header = Header::from_str("10 20").unwrap();
+            assert_eq!(
+                Line::parse(&header, 3, "1 0 2").unwrap_err(),
+                LineError::NonPositiveVertexIndex { index: 0 }
+            );
+            assert_eq!(
+                Line::parse(&header, 3, "-1").unwrap_err(),
+                LineError::NonPositiveVertexIndex { index: -1 }
+            );
+        }
+
         #[test]
         fn parse_default() {
             let header = Header::from_str("100 100").unwrap();
@@ -413,23 +834,88 @@ mod tests {
         #[test]
         fn parse_edge_weight() {
             let header = Header::from_str("100 100 001").unwrap();
-            let line = Line::parse(&header, 3, "1 12.34 10 5678 30 -999").unwrap();
+            let line = Line::parse(&header, 3, "1 12 10 5678 30 0").unwrap();
             assert_eq!(line.from_index, 3);
             assert!(line.vertex_size.is_none());
             assert!(line.vertex_weights.is_none());
             assert_eq!(line.vertices, vec![1, 10, 30]);
-            assert_eq!(line.edge_weights.unwrap(), vec![12.34, 5678.0, -999.0]);
+            assert_eq!(line.edge_weights.unwrap(), vec![12, 5678, 0]);
+
+            assert_eq!(
+                Line::parse(&header, 3, "1 12.34 10 5678").unwrap_err(),
+                LineError::NonIntegralEdgeWeight {
+                    from_index: 3,
+                    to_index: 1,
+                    weight: 12.34
+                }
+            );
+            assert_eq!(
+                Line::parse(&header, 3, "1 12 10 -999").unwrap_err(),
+                LineError::NegativeEdgeWeight {
+                    from_index: 3,
+                    to_index: 10,
+                    weight: -999
+                }
+            );
         }
 
         #[test]
         fn parse_vertex_weight() {
             let header = Header::from_str("100 100 010 3").unwrap();
-            let line = Line::parse(&header, 3, "0.1 -3.0 10 1 10 30").unwrap();
+            let line = Line::parse(&header, 3, "0 3.0 10 1 10 30").unwrap();
             assert_eq!(line.from_index, 3);
             assert!(line.vertex_size.is_none());
             assert!(line.edge_weights.is_none());
             assert_eq!(line.vertices, vec![1, 10, 30]);
-            assert_eq!(line.vertex_weights.unwrap(), vec![0.1, -3.0, 10.0]);
+            assert_eq!(line.vertex_weights.unwrap(), vec![0, 3, 10]);
+
+            assert_eq!(
+                Line::parse(&header, 3, "0.1 3 10 1 10 30").unwrap_err(),
+                LineError::NonIntegralVertexWeight {
+                    from_index: 3,
+                    weight: 0.1
+                }
+            );
+            assert_eq!(
+                Line::parse(&header, 3, "1 -3 10 1 10 30").unwrap_err(),
+                LineError::NegativeVertexWeight {
+                    from_index: 3,
+                    weight: -3
+                }
+            );
+            assert!(matches!(
+                Line::parse(&header, 3, "1 a 10 1 10 30").unwrap_err(),
+                LineError::ParseIntError(_)
+            ));
+        }
+
+        #[test]
+        fn vertex_weight_missing() {
+            let header = Header::from_str("2 0 010 2").unwrap();
+            assert_eq!(
+                Line::parse(&header, 1, "1").unwrap_err(),
+                LineError::VertexWeightMissing {
+                    from_index: 1,
+                    ncon: 2,
+                    actual: 1
+                }
+            );
+            // Isolated vertex still needs weights
+            assert_eq!(
+                Line::parse(&header, 2, "").unwrap_err(),
+                LineError::VertexWeightMissing {
+                    from_index: 2,
+                    ncon: 2,
+                    actual: 0
+                }
+            );
+            assert!(matches!(
+                crate::graph::CSRGraph::from_metis_graph_str("2 0 010 2\n1\n1\n").unwrap_err(),
+                GraphFileError::InvalidLineAt {
+                    line_number: 2,
+                    error: LineError::VertexWeightMissing { .. }
+                }
+            ));
         }
 
         #[test]
@@ -0,0 +1,312 @@
+//! I/O for METIS Mesh format
+
+use std::{
+    cell::Cell,
+    io::{self, BufRead},
+    path::*,
+    str::FromStr,
+};
+
+use super::compress;
+
+/// Example meshes
+pub mod examples {
+    /// Four triangles in a square, with corner nodes 1-4 and center node 5
+    pub const TRIANGLES: &str = r#"
+        4
+        1 2 5
+        2 3 5
+        3 4 5
+        4 1 5
+    "#;
+
+    /// [TRIANGLES] with two weights for each element
+    pub const TRIANGLES_WEIGHTED: &str = r#"
+        4 2
+        1 3 1 2 5
+        2 2 2 3 5
+        3 1 3 4 5
+        4 0 4 1 5
+    "#;
+}
+
+/// Constructable from METIS Mesh format
+pub trait FromMetisMeshFormat: Sized {
+    fn from_metis_mesh_iter(
+        header: &Header,
+        lines: impl Iterator<Item = Result<Line, LineError>>,
+    ) -> Result<Self, MeshFileError>;
+
+    /// Read METIS mesh file as a string (assumes to be small)
+    fn from_metis_mesh_str(input: &str) -> Result<Self, MeshFileError> {
+        Self::from_metis_mesh_lines(input.lines().map(|line| Ok(line.to_string())))
+    }
+
+    /// Read METIS mesh file, which is decompressed if compressed (see [super::compress])
+    fn from_metis_mesh(path: impl AsRef<Path>) -> Result<Self, MeshFileError> {
+        Self::from_metis_mesh_lines(compress::open(path)?.lines())
+    }
+
+    // common default implementations
+    #[doc(hidden)]
+    fn from_metis_mesh_lines(
+        lines: impl Iterator<Item = io::Result<String>>,
+    ) -> Result<Self, MeshFileError> {
+        // The first error in reading lines stops the iteration, and is reported prior to parse errors
+        let read_error = Cell::new(None);
+
+        // Lines starting with `%` are comments, and blank lines are ignored since every element has nodes.
+        // Line numbers are counted from 1 in the file.
+        let mut lines = lines
+            .map_while(|line| line.map_err(|e| read_error.set(Some(e))).ok())
+            .enumerate()
+            .map(|(i, line)| (i + 1, line))
+            .filter(|(_, line)| {
+                let line = line.trim_start();
+                !line.is_empty() && !line.starts_with('%')
+            });
+        let header = match lines.next() {
+            Some((_, header)) => Header::from_str(&header),
+            None => Err(HeaderError::Empty),
+        };
+        if let Some(e) = read_error.take() {
+            return Err(e.into());
+        }
+        let header = header?;
+
+        let mut error_line_number = None;
+        let lines = lines
+            .enumerate()
+            .map(|(element_index, (line_number, line))| {
+                let line = Line::parse(&header, element_index as i32 + 1, &line);
+                if line.is_err() && error_line_number.is_none() {
+                    error_line_number = Some(line_number);
+                }
+                line
+            });
+        let mesh = Self::from_metis_mesh_iter(&header, lines);
+        if let Some(e) = read_error.take() {
+            return Err(e.into());
+        }
+        match mesh {
+            Err(MeshFileError::InvalidLine(error)) => match error_line_number {
+                Some(line_number) => Err(MeshFileError::InvalidLineAt { line_number, error }),
+                None => Err(MeshFileError::InvalidLine(error)),
+            },
+            mesh => mesh,
+        }
+    }
+}
+
+/// Error for invalid line
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
+pub enum LineError {
+    #[error(
+        "Element {element_index} does not have enough weights: {num_weights} weights are required"
+    )]
+    WeightMissing {
+        element_index: i32,
+        num_weights: usize,
+    },
+
+    #[error("Weight of element {element_index} must be non-negative: {weight}")]
+    NegativeWeight { element_index: i32, weight: i32 },
+
+    #[error("Node {node} of element {element_index} is out-of-range: node index starts from 1")]
+    NodeOutOfRange { element_index: i32, node: i32 },
+
+    #[error(transparent)]
+    ParseIntError(#[from] std::num::ParseIntError),
+}
+
+/// Error for invalid header line
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
+pub enum HeaderError {
+    #[error("Header is empty")]
+    Empty,
+
+    #[error("Number of elements must be positive")]
+    NoElement,
+
+    #[error(transparent)]
+    ParseIntError(#[from] std::num::ParseIntError),
+}
+
+/// Errors raised because METIS mesh file is in invalid format.
+#[derive(Debug, thiserror::Error)]
+pub enum MeshFileError {
+    #[error(transparent)]
+    InvalidHeader(#[from] HeaderError),
+
+    #[error(transparent)]
+    InvalidLine(#[from] LineError),
+
+    #[error("Invalid line {line_number}: {error}")]
+    InvalidLineAt {
+        line_number: usize,
+        #[source]
+        error: LineError,
+    },
+
+    #[error("Element size mismatch: actual({actual}) != header({header})")]
+    ElementSizeMismatch { actual: usize, header: usize },
+
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+}
+
+/// Header of METIS Mesh file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    /// Number of elements
+    pub num_elements: usize,
+    /// Number of weights associated with each element. Zero if elements are not weighted.
+    pub num_weights: usize,
+}
+
+impl FromStr for Header {
+    type Err = HeaderError;
+    fn from_str(line: &str) -> Result<Self, HeaderError> {
+        let mut split_iter = line.split_whitespace();
+        let num_elements = split_iter.next().ok_or(HeaderError::Empty)?.parse()?;
+        if num_elements == 0 {
+            return Err(HeaderError::NoElement);
+        }
+        let num_weights = match split_iter.next() {
+            Some(ncon) => ncon.parse()?,
+            None => 0,
+        };
+        Ok(Header {
+            num_elements,
+            num_weights,
+        })
+    }
+}
+
+/// Parsed line in METIS mesh format
+#[derive(Debug)]
+pub struct Line {
+    /// Corresponding element index (1-based)
+    pub element_index: i32,
+    /// Element weights.
+    /// None if Header.num_weights is zero
+    pub weights: Option<Vec<i32>>,
+    /// Nodes of the element (1-based as in the file)
+    pub nodes: Vec<i32>,
+}
+
+impl Line {
+    pub fn parse(header: &Header, element_index: i32, line: &str) -> Result<Self, LineError> {
+        let mut nums = line.split_whitespace();
+        let weights = if header.num_weights > 0 {
+            let mut ws = Vec::with_capacity(header.num_weights);
+            for _ in 0..header.num_weights {
+                let w: i32 = nums
+                    .next()
+                    .ok_or(LineError::WeightMissing {
+                        element_index,
+                        num_weights: header.num_weights,
+                    })?
+                    .parse()?;
+                if w < 0 {
+                    return Err(LineError::NegativeWeight {
+                        element_index,
+                        weight: w,
+                    });
+                }
+                ws.push(w);
+            }
+            Some(ws)
+        } else {
+            None
+        };
+        let nodes = nums.map(|n| n.parse()).collect::<Result<Vec<i32>, _>>()?;
+        for &node in &nodes {
+            if node < 1 {
+                return Err(LineError::NodeOutOfRange {
+                    element_index,
+                    node,
+                });
+            }
+        }
+        Ok(Self {
+            element_index,
+            weights,
+            nodes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod header {
+        use super::*;
+        #[test]
+        fn parse_success() {
+            let header = Header::from_str("10").unwrap();
+            assert_eq!(header.num_elements, 10);
+            assert_eq!(header.num_weights, 0);
+
+            let header = Header::from_str("10  2").unwrap();
+            assert_eq!(header.num_elements, 10);
+            assert_eq!(header.num_weights, 2);
+        }
+
+        #[test]
+        fn parse_fail() {
+            assert_eq!(Header::from_str("").unwrap_err(), HeaderError::Empty);
+            assert_eq!(Header::from_str("0").unwrap_err(), HeaderError::NoElement);
+            assert!(Header::from_str("-3").is_err());
+        }
+    }
+
+    mod line {
+        use super::*;
+
+        #[test]
+        fn parse_default() {
+            let header = Header::from_str("10").unwrap();
+            let line = Line::parse(&header, 3, "1 10  30").unwrap();
+            assert_eq!(line.element_index, 3);
+            assert!(line.weights.is_none());
+            assert_eq!(line.nodes, vec![1, 10, 30]);
+        }
+
+        #[test]
+        fn parse_weights() {
+            let header = Header::from_str("10 2").unwrap();
+            let line = Line::parse(&header, 3, "4 5 1 10 30").unwrap();
+            assert_eq!(line.weights.unwrap(), vec![4, 5]);
+            assert_eq!(line.nodes, vec![1, 10, 30]);
+
+            assert_eq!(
+                Line::parse(&header, 3, "4").unwrap_err(),
+                LineError::WeightMissing {
+                    element_index: 3,
+                    num_weights: 2
+                }
+            );
+            assert_eq!(
+                Line::parse(&header, 3, "4 -1 1 2").unwrap_err(),
+                LineError::NegativeWeight {
+                    element_index: 3,
+                    weight: -1
+                }
+            );
+        }
+
+        #[test]
+        fn node_out_of_range() {
+            let header = Header::from_str("10").unwrap();
+            assert_eq!(
+                Line::parse(&header, 3, "1 0 2").unwrap_err(),
+                LineError::NodeOutOfRange {
+                    element_index: 3,
+                    node: 0
+                }
+            );
+        }
+    }
+}
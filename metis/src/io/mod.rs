@@ -1,9 +1,8 @@
 //! I/O module for file formats defined by METIS
 
+pub(crate) mod bytes;
+pub mod compress;
 pub mod graph;
-
-// TODO implement I/O for METIS mesh format
-// pub mod mesh;
-
-// TODO implement I/O for METIS target partition weights file
-// pub mod partition;
+pub mod mesh;
+pub mod partition;
+pub mod vector;
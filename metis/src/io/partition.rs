@@ -0,0 +1,561 @@
+//! I/O for METIS target partition weights file
+//!
+//! This is the file passed to `gpmetis -tpwgts`. Each line has the form
+//!
+//! ```text
+//! from[-to][:fromcnum[-tocnum]] = weight
+//! ```
+//!
+//! which sets the target weight of parts `from..=to` for constraints `fromcnum..=tocnum` (0-based).
+//! If the constraints are omitted, the weight is set for all constraints.
+//! Weights of unspecified parts are filled evenly so that the weights of each constraint sum to 1,
+//! and it is an error if no weight is left for them as `gpmetis` does.
+//!
+//! Unlike `gpmetis`, a weight of exactly `1` is accepted
+//! so that the file written for a single part can be read back.
+
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    ops::RangeInclusive,
+    path::*,
+    str::FromStr,
+};
+
+/// Example target partition weights files
+pub mod examples {
+    /// Four parts with a single constraint, where part 3 is unspecified and gets `0.1`
+    pub const FOUR_PARTS: &str = r#"
+        0 = 0.3
+        1 = 0.4
+        2 = 0.2
+    "#;
+
+    /// Three parts with two constraints
+    pub const TWO_CONSTRAINTS: &str = r#"
+        0-1:0 = 0.25
+        2:0 = 0.5
+        0:1 = 0.6
+    "#;
+}
+
+/// Allowed error of the sum of target partition weights of each constraint from 1
+pub const TOLERANCE: f32 = 1e-3;
+
+/// Error for invalid line
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
+pub enum LineError {
+    #[error("Part index is missing")]
+    PartMissing,
+
+    #[error("Weight is missing")]
+    WeightMissing,
+
+    #[error("Part {part} is out-of-range: number of parts is {num_parts}")]
+    PartOutOfRange { part: usize, num_parts: usize },
+
+    #[error("Constraint {constraint} is out-of-range: number of constraints is {ncon}")]
+    ConstraintOutOfRange { constraint: usize, ncon: usize },
+
+    #[error("Range {from}-{to} is empty")]
+    EmptyRange { from: usize, to: usize },
+
+    #[error("Weight must be in (0, 1]: {weight}")]
+    InvalidWeight { weight: f32 },
+
+    #[error(transparent)]
+    ParseIntError(#[from] std::num::ParseIntError),
+
+    #[error(transparent)]
+    ParseFloatError(#[from] std::num::ParseFloatError),
+}
+
+/// Errors raised because target partition weights file is in invalid format.
+#[derive(Debug, thiserror::Error)]
+pub enum PartitionWeightsFileError {
+    #[error("Invalid line {line_number}: {error}")]
+    InvalidLine {
+        line_number: usize,
+        error: LineError,
+    },
+
+    #[error("Target partition weights of constraint {constraint} sum to {sum}, which exceeds 1")]
+    SumExceeded { constraint: usize, sum: f32 },
+
+    #[error("Target partition weights of constraint {constraint} sum to {sum}, not 1")]
+    SumMismatch { constraint: usize, sum: f32 },
+
+    #[error("Target partition weights of constraint {constraint} sum to {sum}, and no weight is left for {num_left} unspecified parts")]
+    NoWeightLeft {
+        constraint: usize,
+        sum: f32,
+        num_left: usize,
+    },
+
+    #[error(
+        "Number of parts and constraints must be positive: nparts = {num_parts}, ncon = {ncon}"
+    )]
+    EmptyShape { num_parts: usize, ncon: usize },
+
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+}
+
+/// Parsed line in target partition weights file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    /// Range of parts
+    pub parts: RangeInclusive<usize>,
+    /// Range of constraints. None for all constraints.
+    pub constraints: Option<RangeInclusive<usize>>,
+    /// Target weight of each part in `parts`
+    pub weight: f32,
+}
+
+/// Check weight is a finite number in `(0, 1]`, where `gpmetis` rejects `1`
+fn check_weight(weight: f32) -> Result<(), LineError> {
+    if !weight.is_finite() || weight <= 0.0 || weight > 1.0 {
+        return Err(LineError::InvalidWeight { weight });
+    }
+    Ok(())
+}
+
+/// Parse `from[-to]`
+fn parse_range(s: &str) -> Result<RangeInclusive<usize>, LineError> {
+    let (from, to) = match s.split_once('-') {
+        Some((from, to)) => (from.parse()?, to.parse()?),
+        None => {
+            let from = s.parse()?;
+            (from, from)
+        }
+    };
+    if from > to {
+        return Err(LineError::EmptyRange { from, to });
+    }
+    Ok(from..=to)
+}
+
+impl FromStr for Line {
+    type Err = LineError;
+    fn from_str(line: &str) -> Result<Self, LineError> {
+        // gpmetis removes all spaces before parsing
+        let line: String = line.split_whitespace().collect();
+        let (range, weight) = line.split_once('=').ok_or(LineError::WeightMissing)?;
+        if range.is_empty() {
+            return Err(LineError::PartMissing);
+        }
+        if weight.is_empty() {
+            return Err(LineError::WeightMissing);
+        }
+        let (parts, constraints) = match range.split_once(':') {
+            Some((parts, constraints)) => (parse_range(parts)?, Some(parse_range(constraints)?)),
+            None => (parse_range(range)?, None),
+        };
+        let weight: f32 = weight.parse()?;
+        check_weight(weight)?;
+        Ok(Line {
+            parts,
+            constraints,
+            weight,
+        })
+    }
+}
+
+/// Target partition weights, `tpwgts` in METIS manual
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetPartitionWeights {
+    num_parts: usize,
+    ncon: usize,
+    tpwgts: Vec<f32>,
+}
+
+impl TargetPartitionWeights {
+    /// Same weight `1 / num_parts` for all parts and constraints
+    pub fn uniform(num_parts: usize, ncon: usize) -> Self {
+        assert!(
+            num_parts > 0 && ncon > 0,
+            "num_parts and ncon must be positive"
+        );
+        TargetPartitionWeights {
+            num_parts,
+            ncon,
+            tpwgts: vec![1.0 / num_parts as f32; num_parts * ncon],
+        }
+    }
+
+    /// Create from parsed lines, and fill unspecified parts evenly
+    ///
+    /// Line numbers in errors are the positions in `lines` starting from 1.
+    pub fn from_lines(
+        num_parts: usize,
+        ncon: usize,
+        lines: impl IntoIterator<Item = Line>,
+    ) -> Result<Self, PartitionWeightsFileError> {
+        Self::from_indexed_lines(
+            num_parts,
+            ncon,
+            lines.into_iter().enumerate().map(|(i, line)| (i + 1, line)),
+        )
+    }
+
+    /// Read target partition weights file as a string
+    pub fn from_tpwgts_str(
+        input: &str,
+        num_parts: usize,
+        ncon: usize,
+    ) -> Result<Self, PartitionWeightsFileError> {
+        Self::from_text_lines(
+            num_parts,
+            ncon,
+            input.lines().map(|line| Ok(line.to_string())),
+        )
+    }
+
+    /// Read target partition weights file
+    pub fn from_tpwgts(
+        path: impl AsRef<Path>,
+        num_parts: usize,
+        ncon: usize,
+    ) -> Result<Self, PartitionWeightsFileError> {
+        let f = fs::File::open(path.as_ref())?;
+        Self::from_text_lines(num_parts, ncon, io::BufReader::new(f).lines())
+    }
+
+    fn from_text_lines(
+        num_parts: usize,
+        ncon: usize,
+        lines: impl Iterator<Item = io::Result<String>>,
+    ) -> Result<Self, PartitionWeightsFileError> {
+        let mut parsed = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            let line_number = i + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let line = Line::from_str(&line)
+                .map_err(|error| PartitionWeightsFileError::InvalidLine { line_number, error })?;
+            parsed.push((line_number, line));
+        }
+        Self::from_indexed_lines(num_parts, ncon, parsed)
+    }
+
+    fn from_indexed_lines(
+        num_parts: usize,
+        ncon: usize,
+        lines: impl IntoIterator<Item = (usize, Line)>,
+    ) -> Result<Self, PartitionWeightsFileError> {
+        if num_parts == 0 || ncon == 0 {
+            return Err(PartitionWeightsFileError::EmptyShape { num_parts, ncon });
+        }
+        // Negative for unspecified weights, as gpmetis does
+        let mut tpwgts = vec![-1.0; num_parts * ncon];
+        for (line_number, line) in lines {
+            let invalid = |error| PartitionWeightsFileError::InvalidLine { line_number, error };
+            check_weight(line.weight).map_err(invalid)?;
+            if *line.parts.end() >= num_parts {
+                return Err(invalid(LineError::PartOutOfRange {
+                    part: *line.parts.end(),
+                    num_parts,
+                }));
+            }
+            let constraints = line.constraints.unwrap_or(0..=ncon - 1);
+            if *constraints.end() >= ncon {
+                return Err(invalid(LineError::ConstraintOutOfRange {
+                    constraint: *constraints.end(),
+                    ncon,
+                }));
+            }
+            for part in line.parts {
+                for constraint in constraints.clone() {
+                    tpwgts[part * ncon + constraint] = line.weight;
+                }
+            }
+        }
+
+        for constraint in 0..ncon {
+            let (sum, num_left) = (0..num_parts)
+                .map(|part| tpwgts[part * ncon + constraint])
+                .fold((0.0, 0), |(sum, num_left), w| {
+                    if w < 0.0 {
+                        (sum, num_left + 1)
+                    } else {
+                        (sum + w, num_left)
+                    }
+                });
+            if num_left == 0 {
+                if (sum - 1.0).abs() > TOLERANCE {
+                    return Err(PartitionWeightsFileError::SumMismatch { constraint, sum });
+                }
+                continue;
+            }
+            if sum > 1.0 + TOLERANCE {
+                return Err(PartitionWeightsFileError::SumExceeded { constraint, sum });
+            }
+            if sum >= 1.0 {
+                return Err(PartitionWeightsFileError::NoWeightLeft {
+                    constraint,
+                    sum,
+                    num_left,
+                });
+            }
+            let left = (1.0 - sum) / num_left as f32;
+            for part in 0..num_parts {
+                let w = &mut tpwgts[part * ncon + constraint];
+                if *w < 0.0 {
+                    *w = left;
+                }
+            }
+        }
+
+        Ok(TargetPartitionWeights {
+            num_parts,
+            ncon,
+            tpwgts,
+        })
+    }
+
+    /// Number of parts, `nparts` in METIS manual
+    pub fn num_parts(&self) -> usize {
+        self.num_parts
+    }
+
+    /// Number of constraints, `ncon` in METIS manual
+    pub fn ncon(&self) -> usize {
+        self.ncon
+    }
+
+    /// Target weight of `part` for `constraint`, or `None` if either is out-of-range
+    pub fn get(&self, part: usize, constraint: usize) -> Option<f32> {
+        if part >= self.num_parts || constraint >= self.ncon {
+            return None;
+        }
+        Some(self.tpwgts[part * self.ncon + constraint])
+    }
+
+    /// Flat array of `nparts * ncon` weights, `tpwgts` in METIS manual
+    pub fn tpwgts(&self) -> &[f32] {
+        &self.tpwgts
+    }
+
+    /// Write in target partition weights file format, one line for each part and constraint
+    pub fn write(&self, mut w: impl Write) -> io::Result<()> {
+        for (i, weight) in self.tpwgts.iter().enumerate() {
+            let (part, constraint) = (i / self.ncon, i % self.ncon);
+            if self.ncon == 1 {
+                writeln!(w, "{} = {}", part, weight)?;
+            } else {
+                writeln!(w, "{}:{} = {}", part, constraint, weight)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write target partition weights file
+    pub fn write_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let f = fs::File::create(path.as_ref())?;
+        let mut w = io::BufWriter::new(f);
+        self.write(&mut w)?;
+        w.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod line {
+        use super::*;
+
+        #[test]
+        fn parse_success() {
+            let line = Line::from_str("3 = 0.25").unwrap();
+            assert_eq!(line.parts, 3..=3);
+            assert!(line.constraints.is_none());
+            assert_eq!(line.weight, 0.25);
+
+            let line = Line::from_str("0-2 : 1-2=0.1").unwrap();
+            assert_eq!(line.parts, 0..=2);
+            assert_eq!(line.constraints, Some(1..=2));
+            assert_eq!(line.weight, 0.1);
+        }
+
+        #[test]
+        fn parse_fail() {
+            assert_eq!(Line::from_str("3").unwrap_err(), LineError::WeightMissing);
+            assert_eq!(Line::from_str("3 =").unwrap_err(), LineError::WeightMissing);
+            assert_eq!(Line::from_str("= 0.1").unwrap_err(), LineError::PartMissing);
+            assert_eq!(
+                Line::from_str("3-1 = 0.1").unwrap_err(),
+                LineError::EmptyRange { from: 3, to: 1 }
+            );
+            assert_eq!(
+                Line::from_str("1 = 1.5").unwrap_err(),
+                LineError::InvalidWeight { weight: 1.5 }
+            );
+            assert!(matches!(
+                Line::from_str("a = 0.1").unwrap_err(),
+                LineError::ParseIntError(_)
+            ));
+            assert!(matches!(
+                Line::from_str("1 = a").unwrap_err(),
+                LineError::ParseFloatError(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn four_parts() {
+        let w = TargetPartitionWeights::from_tpwgts_str(examples::FOUR_PARTS, 4, 1).unwrap();
+        assert_eq!(w.num_parts(), 4);
+        assert_eq!(w.ncon(), 1);
+        assert_eq!(w.tpwgts()[..3], [0.3, 0.4, 0.2]);
+        assert!((w.get(3, 0).unwrap() - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn two_constraints() {
+        let w = TargetPartitionWeights::from_tpwgts_str(examples::TWO_CONSTRAINTS, 3, 2).unwrap();
+        assert_eq!(w.tpwgts().len(), 6);
+        assert_eq!(w.get(0, 0).unwrap(), 0.25);
+        assert_eq!(w.get(1, 0).unwrap(), 0.25);
+        assert_eq!(w.get(2, 0).unwrap(), 0.5);
+        assert_eq!(w.get(0, 1).unwrap(), 0.6);
+        assert!((w.get(1, 1).unwrap() - 0.2).abs() < 1e-6);
+        assert!((w.get(2, 1).unwrap() - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn all_constraints() {
+        let w = TargetPartitionWeights::from_tpwgts_str("0 = 0.5", 3, 2).unwrap();
+        assert_eq!(w.get(0, 0).unwrap(), 0.5);
+        assert_eq!(w.get(0, 1).unwrap(), 0.5);
+        assert_eq!(w.get(2, 1).unwrap(), 0.25);
+        assert_eq!(w.get(3, 0), None);
+        assert_eq!(w.get(0, 2), None);
+    }
+
+    #[test]
+    fn invalid() {
+        let err = TargetPartitionWeights::from_tpwgts_str("0 = 0.5\n4 = 0.1", 4, 1).unwrap_err();
+        assert!(matches!(
+            err,
+            PartitionWeightsFileError::InvalidLine {
+                line_number: 2,
+                error: LineError::PartOutOfRange {
+                    part: 4,
+                    num_parts: 4
+                }
+            }
+        ));
+
+        let err = TargetPartitionWeights::from_tpwgts_str("0:1 = 0.5", 4, 1).unwrap_err();
+        assert!(matches!(
+            err,
+            PartitionWeightsFileError::InvalidLine {
+                line_number: 1,
+                error: LineError::ConstraintOutOfRange {
+                    constraint: 1,
+                    ncon: 1
+                }
+            }
+        ));
+
+        let err = TargetPartitionWeights::from_tpwgts_str("0-1 = 0.6", 3, 1).unwrap_err();
+        assert!(matches!(
+            err,
+            PartitionWeightsFileError::SumExceeded { constraint: 0, .. }
+        ));
+
+        let err = TargetPartitionWeights::from_tpwgts_str("0-1 = 0.4", 2, 1).unwrap_err();
+        assert!(matches!(
+            err,
+            PartitionWeightsFileError::SumMismatch { constraint: 0, .. }
+        ));
+
+        let err = TargetPartitionWeights::from_tpwgts_str("0-1 = 0.5", 3, 1).unwrap_err();
+        assert!(matches!(
+            err,
+            PartitionWeightsFileError::NoWeightLeft {
+                constraint: 0,
+                num_left: 1,
+                ..
+            }
+        ));
+
+        let err = TargetPartitionWeights::from_tpwgts_str("0 = NaN", 2, 1).unwrap_err();
+        assert!(matches!(
+            err,
+            PartitionWeightsFileError::InvalidLine {
+                line_number: 1,
+                error: LineError::InvalidWeight { .. }
+            }
+        ));
+        let nan = Line {
+            parts: 0..=0,
+            constraints: None,
+            weight: f32::NAN,
+        };
+        assert!(TargetPartitionWeights::from_lines(2, 1, [nan]).is_err());
+
+        let err = TargetPartitionWeights::from_tpwgts_str("0 = 0.5", 2, 0).unwrap_err();
+        assert!(matches!(
+            err,
+            PartitionWeightsFileError::EmptyShape {
+                num_parts: 2,
+                ncon: 0
+            }
+        ));
+    }
+
+    /// Write and read back
+    fn assert_round_trip(w: &TargetPartitionWeights) {
+        let mut buf = Vec::new();
+        w.write(&mut buf).unwrap();
+        let s = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            &TargetPartitionWeights::from_tpwgts_str(&s, w.num_parts(), w.ncon()).unwrap(),
+            w
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for (num_parts, ncon) in [(1, 1), (1, 3), (3, 1), (4, 2), (7, 3)] {
+            assert_round_trip(&TargetPartitionWeights::uniform(num_parts, ncon));
+        }
+        for (input, num_parts, ncon) in [
+            (examples::FOUR_PARTS, 4, 1),
+            (examples::TWO_CONSTRAINTS, 3, 2),
+            ("0 = 1", 1, 1),
+            ("0 = 0.999", 2, 1),
+            ("0 = 0.5", 3, 2),
+        ] {
+            let w = TargetPartitionWeights::from_tpwgts_str(input, num_parts, ncon).unwrap();
+            assert_round_trip(&w);
+        }
+    }
+
+    #[test]
+    fn write_read() {
+        let w = TargetPartitionWeights::from_tpwgts_str(examples::TWO_CONSTRAINTS, 3, 2).unwrap();
+        let mut buf = Vec::new();
+        w.write(&mut buf).unwrap();
+        let s = String::from_utf8(buf).unwrap();
+        assert_eq!(s.lines().next(), Some("0:0 = 0.25"));
+        assert_eq!(
+            TargetPartitionWeights::from_tpwgts_str(&s, 3, 2).unwrap(),
+            w
+        );
+
+        let w = TargetPartitionWeights::uniform(4, 1);
+        let mut buf = Vec::new();
+        w.write(&mut buf).unwrap();
+        let s = String::from_utf8(buf).unwrap();
+        assert_eq!(s.lines().next(), Some("0 = 0.25"));
+        assert_eq!(
+            TargetPartitionWeights::from_tpwgts_str(&s, 4, 1).unwrap(),
+            w
+        );
+    }
+}
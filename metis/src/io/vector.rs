@@ -0,0 +1,261 @@
+//! I/O for partition and ordering vector files
+//!
+//! `gpmetis` writes the partition of `graph` into `graph.part.N` (see [partition_file_name]),
+//! and `ndmetis` writes the fill-reducing ordering into `graph.iperm` (see [permutation_file_name]).
+//! Both files have a 0-based index for each vertex in each line;
+//! the part index for a partition, and `iperm` for an ordering.
+
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    num::ParseIntError,
+    path::*,
+};
+
+use crate::{
+    metrics::{check_partition, PartitionError},
+    ordering::{Permutation, PermutationError},
+};
+
+/// Errors raised because partition or ordering vector file is in invalid format.
+#[derive(Debug, thiserror::Error)]
+pub enum VectorFileError {
+    #[error("Invalid line {line_number}: {error}")]
+    InvalidLine {
+        line_number: usize,
+        #[source]
+        error: ParseIntError,
+    },
+
+    #[error("File must have an index for each vertex: actual({actual}) != vertices({expected})")]
+    LengthMismatch { actual: usize, expected: usize },
+
+    #[error(transparent)]
+    InvalidPartition(#[from] PartitionError),
+
+    #[error(transparent)]
+    InvalidPermutation(#[from] PermutationError),
+
+    #[error(transparent)]
+    IO(#[from] io::Error),
+}
+
+/// `<graph>.part.<nparts>` as `gpmetis` writes
+pub fn partition_file_name(graph_path: impl AsRef<Path>, nparts: usize) -> PathBuf {
+    append_extension(graph_path.as_ref(), &format!("part.{}", nparts))
+}
+
+/// `<graph>.iperm` as `ndmetis` writes
+pub fn permutation_file_name(graph_path: impl AsRef<Path>) -> PathBuf {
+    append_extension(graph_path.as_ref(), "iperm")
+}
+
+fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    path.into()
+}
+
+/// Read partition vector, and check it has a part index in `0..nparts` for each of `num_vertices` vertices
+pub fn read_partition(
+    reader: impl BufRead,
+    num_vertices: usize,
+    nparts: usize,
+) -> Result<Vec<i32>, VectorFileError> {
+    let part = read_vector(reader, num_vertices)?;
+    check_partition(&part, num_vertices, nparts)?;
+    Ok(part)
+}
+
+/// Read partition vector from a string
+pub fn read_partition_str(
+    input: &str,
+    num_vertices: usize,
+    nparts: usize,
+) -> Result<Vec<i32>, VectorFileError> {
+    read_partition(input.as_bytes(), num_vertices, nparts)
+}
+
+/// Read partition vector file, e.g. `graph.part.4`
+pub fn read_partition_file(
+    path: impl AsRef<Path>,
+    num_vertices: usize,
+    nparts: usize,
+) -> Result<Vec<i32>, VectorFileError> {
+    let f = fs::File::open(path.as_ref())?;
+    read_partition(io::BufReader::new(f), num_vertices, nparts)
+}
+
+/// Write partition vector, one part index for each line
+pub fn write_partition(w: impl Write, part: &[i32]) -> io::Result<()> {
+    write_vector(w, part)
+}
+
+/// Write partition vector file
+pub fn write_partition_file(path: impl AsRef<Path>, part: &[i32]) -> io::Result<()> {
+    write_vector_file(path.as_ref(), part)
+}
+
+/// Read `iperm` of `num_vertices` vertices, and check it is a permutation
+pub fn read_permutation(
+    reader: impl BufRead,
+    num_vertices: usize,
+) -> Result<Permutation, VectorFileError> {
+    let iperm = read_vector(reader, num_vertices)?;
+    Ok(Permutation::from_iperm(iperm)?)
+}
+
+/// Read `iperm` from a string
+pub fn read_permutation_str(
+    input: &str,
+    num_vertices: usize,
+) -> Result<Permutation, VectorFileError> {
+    read_permutation(input.as_bytes(), num_vertices)
+}
+
+/// Read `iperm` file, e.g. `graph.iperm`
+pub fn read_permutation_file(
+    path: impl AsRef<Path>,
+    num_vertices: usize,
+) -> Result<Permutation, VectorFileError> {
+    let f = fs::File::open(path.as_ref())?;
+    read_permutation(io::BufReader::new(f), num_vertices)
+}
+
+/// Write `iperm` of the permutation, one index for each line
+pub fn write_permutation(w: impl Write, permutation: &Permutation) -> io::Result<()> {
+    write_vector(w, permutation.iperm())
+}
+
+/// Write `iperm` file
+pub fn write_permutation_file(path: impl AsRef<Path>, permutation: &Permutation) -> io::Result<()> {
+    write_vector_file(path.as_ref(), permutation.iperm())
+}
+
+/// Read an integer for each line. Blank lines are skipped.
+fn read_vector(reader: impl BufRead, num_vertices: usize) -> Result<Vec<i32>, VectorFileError> {
+    let mut vector = Vec::with_capacity(num_vertices);
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let index = line.parse().map_err(|error| VectorFileError::InvalidLine {
+            line_number: i + 1,
+            error,
+        })?;
+        vector.push(index);
+    }
+    if vector.len() != num_vertices {
+        return Err(VectorFileError::LengthMismatch {
+            actual: vector.len(),
+            expected: num_vertices,
+        });
+    }
+    Ok(vector)
+}
+
+fn write_vector(mut w: impl Write, vector: &[i32]) -> io::Result<()> {
+    for index in vector {
+        writeln!(w, "{}", index)?;
+    }
+    Ok(())
+}
+
+fn write_vector_file(path: &Path, vector: &[i32]) -> io::Result<()> {
+    let f = fs::File::create(path)?;
+    let mut w = io::BufWriter::new(f);
+    write_vector(&mut w, vector)?;
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_name() {
+        assert_eq!(
+            partition_file_name("data/4elt.graph", 8),
+            PathBuf::from("data/4elt.graph.part.8")
+        );
+        assert_eq!(
+            permutation_file_name("data/4elt.graph"),
+            PathBuf::from("data/4elt.graph.iperm")
+        );
+    }
+
+    #[test]
+    fn partition() {
+        let part = read_partition_str("0\n1\n1\n0\n", 4, 2).unwrap();
+        assert_eq!(part, vec![0, 1, 1, 0]);
+
+        let mut buf = Vec::new();
+        write_partition(&mut buf, &part).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "0\n1\n1\n0\n");
+    }
+
+    #[test]
+    fn invalid_partition() {
+        assert!(matches!(
+            read_partition_str("0\n1\n", 3, 2).unwrap_err(),
+            VectorFileError::LengthMismatch {
+                actual: 2,
+                expected: 3
+            }
+        ));
+        assert!(matches!(
+            read_partition_str("0\n2\n1\n", 3, 2).unwrap_err(),
+            VectorFileError::InvalidPartition(PartitionError::OutOfRange {
+                vertex: 1,
+                part: 2,
+                nparts: 2
+            })
+        ));
+        assert!(matches!(
+            read_partition_str("0\n\nx\n", 3, 2).unwrap_err(),
+            VectorFileError::InvalidLine { line_number: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn permutation() {
+        let p = read_permutation_str("2\n0\n1\n", 3).unwrap();
+        assert_eq!(p.iperm(), &[2, 0, 1]);
+        assert_eq!(p.perm(), &[1, 2, 0]);
+
+        let mut buf = Vec::new();
+        write_permutation(&mut buf, &p).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "2\n0\n1\n");
+
+        assert!(matches!(
+            read_permutation_str("2\n0\n2\n", 3).unwrap_err(),
+            VectorFileError::InvalidPermutation(PermutationError::Duplicated { index: 2 })
+        ));
+        assert!(matches!(
+            read_permutation_str("2\n0\n", 3).unwrap_err(),
+            VectorFileError::LengthMismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn write_read_file() {
+        let dir = std::env::temp_dir().join(format!("metis-vector-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let graph = dir.join("grid.graph");
+
+        let part = vec![0, 2, 1, 2];
+        let path = partition_file_name(&graph, 3);
+        write_partition_file(&path, &part).unwrap();
+        assert_eq!(read_partition_file(&path, 4, 3).unwrap(), part);
+
+        let p = Permutation::from_perm(vec![3, 1, 0, 2]).unwrap();
+        let path = permutation_file_name(&graph);
+        write_permutation_file(&path, &p).unwrap();
+        assert_eq!(read_permutation_file(&path, 4).unwrap(), p);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
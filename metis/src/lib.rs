@@ -3,3 +3,9 @@
 pub mod error;
 pub mod graph;
 pub mod io;
+pub mod mesh;
+pub mod metrics;
+pub mod options;
+pub mod ordering;
+pub mod partition;
+pub mod separator;
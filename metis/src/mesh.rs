@@ -0,0 +1,442 @@
+//! Mesh structures
+
+use metis_sys::*;
+use std::{os::raw::c_void, ptr::null_mut, slice};
+
+use crate::{
+    error::{self, Error, MetisErrorCodeCheck},
+    graph::CSRGraph,
+    io::mesh::*,
+};
+
+/// Mesh in compressed sparse row (CSR) format
+///
+/// Nodes of the element `i` are stored in `eind[eptr[i]..eptr[i + 1]]` with 0-based index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    /// `eptr` in METIS manual
+    eptr: Vec<i32>,
+    /// `eind` in METIS manual
+    eind: Vec<i32>,
+    /// Number of nodes, `nn` in METIS manual
+    num_nodes: usize,
+    /// Number of weights of each element
+    num_weights: usize,
+    /// Element weights, `num_weights` weights for each element
+    element_weights: Option<Vec<i32>>,
+}
+
+impl Mesh {
+    /// Create from `eptr` and `eind` with 0-based node indices in `0..num_nodes`
+    ///
+    /// `element_weights` has the same number of weights for each element if given.
+    pub fn new(
+        eptr: Vec<i32>,
+        eind: Vec<i32>,
+        num_nodes: usize,
+        element_weights: Option<Vec<i32>>,
+    ) -> error::Result<Self> {
+        let api_name = "Mesh::new";
+        if eptr.first() != Some(&0) {
+            return Err(Error::invalid_input(api_name, "eptr must start with 0"));
+        }
+        if let Some(i) = eptr.windows(2).position(|w| w[0] > w[1]) {
+            return Err(Error::invalid_input(
+                api_name,
+                format!(
+                    "eptr must be non-decreasing, but eptr[{}] > eptr[{}]",
+                    i,
+                    i + 1
+                ),
+            ));
+        }
+        let num_elements = eptr.len() - 1;
+        error::check_len(api_name, "eind", eind.len(), eptr[num_elements] as usize)?;
+        if num_nodes > idx_t::MAX as usize {
+            return Err(Error::invalid_input(
+                api_name,
+                format!("num_nodes is too large: {}", num_nodes),
+            ));
+        }
+        if let Some((i, node)) = eind
+            .iter()
+            .enumerate()
+            .find(|(_, &node)| node < 0 || node as usize >= num_nodes)
+        {
+            return Err(Error::invalid_input(
+                api_name,
+                format!("eind[{}] = {} is out of 0..{}", i, node, num_nodes),
+            ));
+        }
+
+        let num_weights = match &element_weights {
+            Some(ws) => {
+                if num_elements == 0 || ws.is_empty() || ws.len() % num_elements != 0 {
+                    return Err(Error::invalid_input(
+                        api_name,
+                        format!(
+                            "element_weights must have the same positive number of weights for each of {} elements, but has {}",
+                            num_elements,
+                            ws.len()
+                        ),
+                    ));
+                }
+                error::check_non_negative(api_name, "element_weights", ws)?;
+                ws.len() / num_elements
+            }
+            None => 0,
+        };
+        Ok(Mesh {
+            eptr,
+            eind,
+            num_nodes,
+            num_weights,
+            element_weights,
+        })
+    }
+
+    /// Number of elements, `ne` in METIS manual
+    pub fn num_elements(&self) -> usize {
+        self.eptr.len() - 1
+    }
+
+    /// Number of nodes, `nn` in METIS manual
+    pub fn num_nodes(&self) -> usize {
+        self.num_nodes
+    }
+
+    /// `eptr` in METIS manual
+    pub fn eptr(&self) -> &[i32] {
+        &self.eptr
+    }
+
+    /// `eind` in METIS manual
+    pub fn eind(&self) -> &[i32] {
+        &self.eind
+    }
+
+    /// Number of weights of each element. Zero if elements are not weighted.
+    pub fn num_weights(&self) -> usize {
+        self.num_weights
+    }
+
+    /// Element weights, `num_weights` weights for each element
+    pub fn element_weights(&self) -> Option<&[i32]> {
+        self.element_weights.as_deref()
+    }
+
+    /// Nodes of element `i`
+    pub fn element(&self, i: usize) -> &[i32] {
+        &self.eind[self.eptr[i] as usize..self.eptr[i + 1] as usize]
+    }
+
+    /// Dual graph of the mesh, where two elements are connected if they share at least `ncommon` nodes
+    ///
+    /// `ncommon = 2` for triangle meshes and `ncommon = 3` for tetrahedral meshes, for example.
+    pub fn to_dual_graph(&self, ncommon: usize) -> error::Result<CSRGraph> {
+        let api_name = "METIS_MeshToDual";
+        let mut ncommon = check_ncommon(api_name, ncommon)?;
+        self.to_graph(
+            self.num_elements(),
+            |ne, nn, eptr, eind, numflag, xadj, adjncy| unsafe {
+                METIS_MeshToDual(ne, nn, eptr, eind, &mut ncommon, numflag, xadj, adjncy)
+                    .check(api_name)
+            },
+        )
+    }
+
+    /// Nodal graph of the mesh, where two nodes are connected if they belong to the same element
+    pub fn to_nodal_graph(&self) -> error::Result<CSRGraph> {
+        self.to_graph(
+            self.num_nodes(),
+            |ne, nn, eptr, eind, numflag, xadj, adjncy| unsafe {
+                METIS_MeshToNodal(ne, nn, eptr, eind, numflag, xadj, adjncy)
+                    .check("METIS_MeshToNodal")
+            },
+        )
+    }
+
+    /// Call `METIS_MeshToDual` or `METIS_MeshToNodal`, and copy the resulting graph of `nvtxs` vertices
+    #[allow(clippy::type_complexity)]
+    fn to_graph(
+        &self,
+        nvtxs: usize,
+        mesh_to_graph: impl FnOnce(
+            *mut idx_t,
+            *mut idx_t,
+            *mut idx_t,
+            *mut idx_t,
+            *mut idx_t,
+            *mut *mut idx_t,
+            *mut *mut idx_t,
+        ) -> error::Result<()>,
+    ) -> error::Result<CSRGraph> {
+        // METIS does not take `const` pointers, so pass copies of the inputs
+        let mut eptr = self.eptr.clone();
+        let mut eind = self.eind.clone();
+        let mut ne = self.num_elements() as idx_t;
+        let mut nn = self.num_nodes as idx_t;
+        let mut numflag = 0;
+
+        let mut xadj = MetisArray::null();
+        let mut adjncy = MetisArray::null();
+        mesh_to_graph(
+            &mut ne,
+            &mut nn,
+            eptr.as_mut_ptr(),
+            eind.as_mut_ptr(),
+            &mut numflag,
+            &mut xadj.ptr,
+            &mut adjncy.ptr,
+        )?;
+
+        let xadj = unsafe { xadj.as_slice(nvtxs + 1) }.to_vec();
+        let adjncy = unsafe { adjncy.as_slice(xadj[nvtxs] as usize) }.to_vec();
+        Ok(CSRGraph::from_raw_parts(xadj, adjncy))
+    }
+}
+
+/// Array allocated by METIS, which is released by `METIS_Free` on drop
+struct MetisArray {
+    ptr: *mut idx_t,
+}
+
+impl MetisArray {
+    fn null() -> Self {
+        MetisArray { ptr: null_mut() }
+    }
+
+    /// # Safety
+    /// `ptr` must be non-null and point to at least `len` elements
+    unsafe fn as_slice(&self, len: usize) -> &[idx_t] {
+        assert!(!self.ptr.is_null(), "METIS did not allocate array");
+        slice::from_raw_parts(self.ptr, len)
+    }
+}
+
+impl Drop for MetisArray {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe {
+                METIS_Free(self.ptr as *mut c_void);
+            }
+        }
+    }
+}
+
+/// Check `ncommon` is positive and fits in `idx_t`
+pub(crate) fn check_ncommon(api_name: &str, ncommon: usize) -> error::Result<idx_t> {
+    if ncommon == 0 || ncommon > idx_t::MAX as usize {
+        return Err(Error::invalid_input(
+            api_name,
+            format!("ncommon must be in 1..={}, but is {}", idx_t::MAX, ncommon),
+        ));
+    }
+    Ok(ncommon as idx_t)
+}
+
+impl FromMetisMeshFormat for Mesh {
+    fn from_metis_mesh_iter(
+        header: &Header,
+        lines: impl Iterator<Item = Result<Line, LineError>>,
+    ) -> Result<Self, MeshFileError> {
+        let mut eptr = Vec::with_capacity(header.num_elements + 1);
+        let mut eind = Vec::new();
+        let mut element_weights = if header.num_weights > 0 {
+            Some(Vec::with_capacity(header.num_elements * header.num_weights))
+        } else {
+            None
+        };
+        let mut num_nodes = 0;
+        eptr.push(0);
+        for line in lines {
+            let line = line?;
+            for node in line.nodes {
+                // 1-based in file
+                num_nodes = num_nodes.max(node as usize);
+                eind.push(node - 1);
+            }
+            eptr.push(eind.len() as i32);
+            if let (Some(ws), Some(line_ws)) = (element_weights.as_mut(), line.weights) {
+                ws.extend(line_ws);
+            }
+        }
+        if eptr.len() != header.num_elements + 1 {
+            return Err(MeshFileError::ElementSizeMismatch {
+                actual: eptr.len() - 1,
+                header: header.num_elements,
+            });
+        }
+        Ok(Mesh {
+            eptr,
+            eind,
+            num_nodes,
+            num_weights: header.num_weights,
+            element_weights,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triangles() {
+        let mesh = Mesh::from_metis_mesh_str(examples::TRIANGLES).unwrap();
+        assert_eq!(mesh.num_elements(), 4);
+        assert_eq!(mesh.num_nodes(), 5);
+        assert_eq!(mesh.eptr(), &[0, 3, 6, 9, 12]);
+        assert_eq!(mesh.element(3), &[3, 0, 4]);
+        assert!(mesh.element_weights().is_none());
+    }
+
+    #[test]
+    fn triangles_weighted() {
+        let mesh = Mesh::from_metis_mesh_str(examples::TRIANGLES_WEIGHTED).unwrap();
+        assert_eq!(mesh.num_weights(), 2);
+        assert_eq!(mesh.element_weights().unwrap(), &[1, 3, 2, 2, 3, 1, 4, 0]);
+        assert_eq!(mesh, {
+            let mut mesh = Mesh::from_metis_mesh_str(examples::TRIANGLES).unwrap();
+            mesh.num_weights = 2;
+            mesh.element_weights = Some(vec![1, 3, 2, 2, 3, 1, 4, 0]);
+            mesh
+        });
+    }
+
+    #[test]
+    fn new() {
+        let eptr = vec![0, 3, 6, 9, 12];
+        let eind = vec![0, 1, 4, 1, 2, 4, 2, 3, 4, 3, 0, 4];
+        let mesh = Mesh::new(eptr.clone(), eind.clone(), 5, None).unwrap();
+        assert_eq!(
+            mesh,
+            Mesh::from_metis_mesh_str(examples::TRIANGLES).unwrap()
+        );
+
+        let weights = vec![1, 3, 2, 2, 3, 1, 4, 0];
+        let mesh = Mesh::new(eptr.clone(), eind.clone(), 5, Some(weights)).unwrap();
+        assert_eq!(
+            mesh,
+            Mesh::from_metis_mesh_str(examples::TRIANGLES_WEIGHTED).unwrap()
+        );
+
+        for (eptr, eind, num_nodes, weights) in [
+            (vec![], vec![], 0, None),
+            (vec![1, 3], vec![0, 1, 2], 3, None),
+            (vec![0, 3, 2], vec![0, 1, 2], 3, None),
+            (vec![0, 3], vec![0, 1], 3, None),
+            (vec![0, 3], vec![0, 1, 3], 3, None),
+            (vec![0, 3], vec![0, 1, -1], 3, None),
+            (
+                vec![0, 3, 6],
+                vec![0, 1, 2, 1, 2, 3],
+                4,
+                Some(vec![1, 2, 3]),
+            ),
+            (vec![0, 3], vec![0, 1, 2], 3, Some(vec![])),
+            (vec![0, 3], vec![0, 1, 2], 3, Some(vec![-1])),
+        ] {
+            assert!(matches!(
+                Mesh::new(eptr, eind, num_nodes, weights).unwrap_err(),
+                Error::InvalidInput { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn comments() {
+        let mesh = Mesh::from_metis_mesh_str("% comment\n2\n1 2 3\n% comment\n2 3 4").unwrap();
+        assert_eq!(mesh.num_elements(), 2);
+        assert_eq!(mesh.num_nodes(), 4);
+
+        // Blank lines are ignored
+        let mesh = Mesh::from_metis_mesh_str("\n2\n1 2 3\n\n  \n2 3 4\n\n").unwrap();
+        assert_eq!(mesh.num_elements(), 2);
+    }
+
+    #[test]
+    fn error_line_number() {
+        let err = Mesh::from_metis_mesh_str("% comment\n2\n1 2 3\n\n% comment\n2 0 4").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid line 6: Node 0 of element 2 is out-of-range: node index starts from 1"
+        );
+        assert!(matches!(
+            err,
+            MeshFileError::InvalidLineAt {
+                line_number: 6,
+                error: LineError::NodeOutOfRange {
+                    element_index: 2,
+                    node: 0
+                }
+            }
+        ));
+    }
+
+    #[test]
+    fn element_size_mismatch() {
+        let err = Mesh::from_metis_mesh_str("3\n1 2 3\n2 3 4").unwrap_err();
+        assert!(matches!(
+            err,
+            MeshFileError::ElementSizeMismatch {
+                actual: 2,
+                header: 3
+            }
+        ));
+    }
+
+    #[test]
+    fn dual_graph() {
+        let mesh = Mesh::from_metis_mesh_str(examples::TRIANGLES).unwrap();
+        // Each triangle shares an edge with its two neighbours
+        let graph = mesh.to_dual_graph(2).unwrap();
+        assert_eq!(graph.xadj(), &[0, 2, 4, 6, 8]);
+        for v in 0..4 {
+            let mut nbrs = graph.adjncy()[2 * v..2 * v + 2].to_vec();
+            nbrs.sort();
+            let mut expected = vec![(v as i32 + 1) % 4, (v as i32 + 3) % 4];
+            expected.sort();
+            assert_eq!(nbrs, expected);
+        }
+
+        // All triangles share the center node
+        let graph = mesh.to_dual_graph(1).unwrap();
+        assert_eq!(graph.xadj(), &[0, 3, 6, 9, 12]);
+
+        for ncommon in [0, idx_t::MAX as usize + 1] {
+            assert!(matches!(
+                mesh.to_dual_graph(ncommon).unwrap_err(),
+                Error::InvalidInput { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn nodal_graph() {
+        let mesh = Mesh::from_metis_mesh_str(examples::TRIANGLES).unwrap();
+        let graph = mesh.to_nodal_graph().unwrap();
+        assert_eq!(graph.num_vertices(), 5);
+        // Corner nodes are connected with two corners and the center
+        assert_eq!(graph.xadj(), &[0, 3, 6, 9, 12, 16]);
+        let mut center = graph.adjncy()[12..16].to_vec();
+        center.sort();
+        assert_eq!(center, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn metis_mesh_file() {
+        let mesh = Mesh::from_metis_mesh(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../metis-src/metis-5.1.0/graphs/metis.mesh"
+        ))
+        .unwrap();
+        assert_eq!(mesh.num_elements(), 7434);
+        assert_eq!(mesh.num_nodes(), 4038);
+
+        let graph = mesh.to_dual_graph(2).unwrap();
+        assert_eq!(graph.num_vertices(), 7434);
+        let graph = mesh.to_nodal_graph().unwrap();
+        assert_eq!(graph.num_vertices(), 4038);
+    }
+}
@@ -0,0 +1,383 @@
+//! Quality metrics of graph partitioning
+//!
+//! These are the statistics printed by `gpmetis` program of METIS (see `programs/stat.c`).
+
+use crate::graph::CSRGraph;
+
+/// Error for a partition vector inconsistent with the graph
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
+pub enum PartitionError {
+    #[error("Partition must have a part index for each vertex: actual({actual}) != vertices({expected})")]
+    LengthMismatch { actual: usize, expected: usize },
+
+    #[error("Part index of vertex {vertex} is out-of-range: {part} (nparts = {nparts})")]
+    OutOfRange {
+        vertex: usize,
+        part: i32,
+        nparts: usize,
+    },
+
+    #[error("Target part weights must have nparts * ncon = {expected} elements, but has {actual}")]
+    TargetWeightsMismatch { actual: usize, expected: usize },
+
+    #[error("Target part weight tpwgts[{index}] must be a positive finite number: {weight}")]
+    InvalidTargetWeight { index: usize, weight: f32 },
+}
+
+/// Quality report of a partition
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PartitionReport {
+    /// Number of parts
+    pub num_parts: usize,
+    /// Total weight of the edges between different parts
+    pub edge_cut: i64,
+    /// Total communication volume
+    pub communication_volume: i64,
+    /// Total vertex weights of each part for each constraint, `part_weights[part][constraint]`
+    pub part_weights: Vec<Vec<i64>>,
+    /// Load imbalance of each constraint, i.e. the maximum ratio of the part weight to its target.
+    /// `1.0` means perfectly balanced.
+    pub imbalance: Vec<f64>,
+    /// Number of vertices which have a neighbour in another part
+    pub num_boundary_vertices: usize,
+    /// Maximum number of parts adjacent to a part
+    pub max_neighbouring_parts: usize,
+}
+
+impl PartitionReport {
+    /// Compute all metrics of the partition `part` of `graph` into `nparts` parts
+    ///
+    /// `tpwgts` is the target weight of each part as in [crate::partition::partition_kway_with_options],
+    /// and the parts are assumed to be equally weighted if `None`.
+    pub fn new(
+        graph: &CSRGraph,
+        part: &[i32],
+        nparts: usize,
+        tpwgts: Option<&[f32]>,
+    ) -> Result<Self, PartitionError> {
+        Ok(PartitionReport {
+            num_parts: nparts,
+            edge_cut: edge_cut(graph, part, nparts)?,
+            communication_volume: communication_volume(graph, part, nparts)?,
+            part_weights: part_weights(graph, part, nparts)?,
+            imbalance: imbalance(graph, part, nparts, tpwgts)?,
+            num_boundary_vertices: boundary_vertices(graph, part, nparts)?.len(),
+            max_neighbouring_parts: max_neighbouring_parts(graph, part, nparts)?,
+        })
+    }
+}
+
+/// Total weight of the edges between different parts
+pub fn edge_cut(graph: &CSRGraph, part: &[i32], nparts: usize) -> Result<i64, PartitionError> {
+    check_partition(part, graph.num_vertices(), nparts)?;
+    let mut cut = 0;
+    for v in 0..graph.num_vertices() {
+        for (e, w) in neighbours(graph, v) {
+            if part[v] != part[w] {
+                cut += graph.adjwgt().map_or(1, |adjwgt| adjwgt[e] as i64);
+            }
+        }
+    }
+    // Each edge is counted from both ends
+    Ok(cut / 2)
+}
+
+/// Total communication volume
+///
+/// Each vertex contributes its size (1 if not given) times the number of other parts its neighbours belong to.
+pub fn communication_volume(
+    graph: &CSRGraph,
+    part: &[i32],
+    nparts: usize,
+) -> Result<i64, PartitionError> {
+    check_partition(part, graph.num_vertices(), nparts)?;
+    let mut volume = 0;
+    // `marker[p] == v` if part `p` is already counted for vertex `v`
+    let mut marker = vec![usize::MAX; nparts];
+    for v in 0..graph.num_vertices() {
+        marker[part[v] as usize] = v;
+        let size = graph.vsize().map_or(1, |vsize| vsize[v] as i64);
+        for (_, w) in neighbours(graph, v) {
+            let p = part[w] as usize;
+            if marker[p] != v {
+                marker[p] = v;
+                volume += size;
+            }
+        }
+    }
+    Ok(volume)
+}
+
+/// Total vertex weights of each part for each constraint, `[part][constraint]`
+///
+/// Every vertex has a unit weight if the graph is not weighted.
+pub fn part_weights(
+    graph: &CSRGraph,
+    part: &[i32],
+    nparts: usize,
+) -> Result<Vec<Vec<i64>>, PartitionError> {
+    check_partition(part, graph.num_vertices(), nparts)?;
+    let ncon = graph.ncon();
+    let mut weights = vec![vec![0; ncon]; nparts];
+    for (v, &p) in part.iter().enumerate() {
+        for (j, weight) in weights[p as usize].iter_mut().enumerate() {
+            *weight += graph.vwgt().map_or(1, |vwgt| vwgt[v * ncon + j] as i64);
+        }
+    }
+    Ok(weights)
+}
+
+/// Load imbalance of each constraint
+///
+/// This is the maximum over parts of the ratio of the part weight to its target weight,
+/// computed from `tpwgts` (`nparts * ncon` elements) or equal targets if `None`.
+pub fn imbalance(
+    graph: &CSRGraph,
+    part: &[i32],
+    nparts: usize,
+    tpwgts: Option<&[f32]>,
+) -> Result<Vec<f64>, PartitionError> {
+    let ncon = graph.ncon();
+    if let Some(tpwgts) = tpwgts {
+        if tpwgts.len() != nparts * ncon {
+            return Err(PartitionError::TargetWeightsMismatch {
+                actual: tpwgts.len(),
+                expected: nparts * ncon,
+            });
+        }
+        if let Some((index, &weight)) = tpwgts
+            .iter()
+            .enumerate()
+            .find(|(_, &w)| !(w.is_finite() && w > 0.0))
+        {
+            return Err(PartitionError::InvalidTargetWeight { index, weight });
+        }
+    }
+    let weights = part_weights(graph, part, nparts)?;
+    Ok((0..ncon)
+        .map(|j| {
+            let total: i64 = weights.iter().map(|w| w[j]).sum();
+            if total == 0 {
+                return 1.0;
+            }
+            (0..nparts)
+                .map(|p| {
+                    let target = tpwgts.map_or(1.0 / nparts as f64, |t| t[p * ncon + j] as f64);
+                    weights[p][j] as f64 / (target * total as f64)
+                })
+                .fold(0.0, f64::max)
+        })
+        .collect())
+}
+
+/// Vertices which have a neighbour in another part, in ascending order
+pub fn boundary_vertices(
+    graph: &CSRGraph,
+    part: &[i32],
+    nparts: usize,
+) -> Result<Vec<usize>, PartitionError> {
+    check_partition(part, graph.num_vertices(), nparts)?;
+    Ok((0..graph.num_vertices())
+        .filter(|&v| neighbours(graph, v).any(|(_, w)| part[v] != part[w]))
+        .collect())
+}
+
+/// Maximum number of parts adjacent to a part, i.e. the maximum degree of the subdomain graph
+pub fn max_neighbouring_parts(
+    graph: &CSRGraph,
+    part: &[i32],
+    nparts: usize,
+) -> Result<usize, PartitionError> {
+    check_partition(part, graph.num_vertices(), nparts)?;
+    // Visit vertices part by part, and count the adjacent parts of the current part
+    let mut vertices: Vec<usize> = (0..graph.num_vertices()).collect();
+    vertices.sort_unstable_by_key(|&v| part[v]);
+    // `marker[q] == p` if part `q` is already counted as adjacent to part `p`
+    let mut marker = vec![usize::MAX; nparts];
+    let mut current = usize::MAX;
+    let mut count = 0;
+    let mut max = 0;
+    for v in vertices {
+        let p = part[v] as usize;
+        if p != current {
+            current = p;
+            count = 0;
+        }
+        for (_, w) in neighbours(graph, v) {
+            let q = part[w] as usize;
+            if q != p && marker[q] != p {
+                marker[q] = p;
+                count += 1;
+                max = max.max(count);
+            }
+        }
+    }
+    Ok(max)
+}
+
+/// Check `part` has a part index in `0..nparts` for each of `num_vertices` vertices
+pub fn check_partition(
+    part: &[i32],
+    num_vertices: usize,
+    nparts: usize,
+) -> Result<(), PartitionError> {
+    if part.len() != num_vertices {
+        return Err(PartitionError::LengthMismatch {
+            actual: part.len(),
+            expected: num_vertices,
+        });
+    }
+    if let Some((vertex, &p)) = part
+        .iter()
+        .enumerate()
+        .find(|(_, &p)| p < 0 || p as usize >= nparts)
+    {
+        return Err(PartitionError::OutOfRange {
+            vertex,
+            part: p,
+            nparts,
+        });
+    }
+    Ok(())
+}
+
+/// Edge indices and 0-based neighbours of vertex `v`
+fn neighbours(graph: &CSRGraph, v: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let offset = graph.numbering().offset();
+    let begin = (graph.xadj()[v] - offset) as usize;
+    let end = (graph.xadj()[v + 1] - offset) as usize;
+    (begin..end).map(move |e| (e, (graph.adjncy()[e] - offset) as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{io::graph::*, options::Numbering, partition::partition_kway};
+
+    /// 5x3 grid split into the left 2 columns and the right 3 columns
+    fn grid() -> (CSRGraph, Vec<i32>) {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let part = (0..15).map(|v| if v % 5 < 2 { 0 } else { 1 }).collect();
+        (graph, part)
+    }
+
+    #[test]
+    fn grid_bisection() {
+        let (graph, part) = grid();
+        let report = PartitionReport::new(&graph, &part, 2, None).unwrap();
+        assert_eq!(
+            report,
+            PartitionReport {
+                num_parts: 2,
+                edge_cut: 3,
+                communication_volume: 6,
+                part_weights: vec![vec![6], vec![9]],
+                imbalance: vec![1.2],
+                num_boundary_vertices: 6,
+                max_neighbouring_parts: 1,
+            }
+        );
+        assert_eq!(
+            boundary_vertices(&graph, &part, 2).unwrap(),
+            vec![1, 2, 6, 7, 11, 12]
+        );
+
+        // Same results for 1-based arrays
+        let fortran = graph.with_numbering(Numbering::Fortran);
+        assert_eq!(
+            PartitionReport::new(&fortran, &part, 2, None).unwrap(),
+            report
+        );
+    }
+
+    #[test]
+    fn weighted() {
+        let (graph, part) = grid();
+        let vwgt = (0..15).flat_map(|v| [1, v]).collect();
+        let graph = graph
+            .with_vertex_weights(2, vwgt)
+            .unwrap()
+            .with_vertex_sizes(vec![2; 15])
+            .unwrap()
+            .with_edge_weights(vec![3; 44])
+            .unwrap();
+        let report = PartitionReport::new(&graph, &part, 2, Some(&[0.4, 0.5, 0.6, 0.5])).unwrap();
+        assert_eq!(report.edge_cut, 9);
+        assert_eq!(report.communication_volume, 12);
+        assert_eq!(report.part_weights, vec![vec![6, 33], vec![9, 72]]);
+        assert_eq!(report.imbalance.len(), 2);
+        assert!((report.imbalance[0] - 1.0).abs() < 1e-6);
+        assert!((report.imbalance[1] - 72.0 / 52.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn kway() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let partition = partition_kway(&graph, 3).unwrap();
+        let report = PartitionReport::new(&graph, &partition.part, 3, None).unwrap();
+        // METIS reports edge-cut as `objval` by default
+        assert_eq!(report.edge_cut, partition.objval as i64);
+        assert!(report.max_neighbouring_parts <= 2);
+    }
+
+    #[test]
+    fn neighbouring_parts() {
+        // Each column is a part, and inner columns are adjacent to two parts
+        let (graph, _) = grid();
+        let part: Vec<i32> = (0..15).map(|v| v % 5).collect();
+        assert_eq!(max_neighbouring_parts(&graph, &part, 5).unwrap(), 2);
+
+        // Memory does not grow quadratically with the number of parts
+        assert_eq!(max_neighbouring_parts(&graph, &part, 1_000_000).unwrap(), 2);
+    }
+
+    #[test]
+    fn invalid_part() {
+        let (graph, part) = grid();
+        assert_eq!(
+            edge_cut(&graph, &part[..14], 2).unwrap_err(),
+            PartitionError::LengthMismatch {
+                actual: 14,
+                expected: 15
+            }
+        );
+        assert_eq!(
+            edge_cut(&graph, &part, 1).unwrap_err(),
+            PartitionError::OutOfRange {
+                vertex: 2,
+                part: 1,
+                nparts: 1
+            }
+        );
+        assert_eq!(
+            imbalance(&graph, &part, 2, Some(&[1.0])).unwrap_err(),
+            PartitionError::TargetWeightsMismatch {
+                actual: 1,
+                expected: 2
+            }
+        );
+        for weight in [0.0, -0.5, f32::NAN, f32::INFINITY] {
+            assert!(matches!(
+                imbalance(&graph, &part, 2, Some(&[0.5, weight])).unwrap_err(),
+                PartitionError::InvalidTargetWeight { index: 1, .. }
+            ));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize() {
+        let (graph, part) = grid();
+        let report = PartitionReport::new(&graph, &part, 2, None).unwrap();
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(
+            json,
+            r#"{"num_parts":2,"edge_cut":3,"communication_volume":6,"part_weights":[[6],[9]],"imbalance":[1.2],"num_boundary_vertices":6,"max_neighbouring_parts":1}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<PartitionReport>(&json).unwrap(),
+            report
+        );
+    }
+}
@@ -0,0 +1,569 @@
+//! Options of METIS routines, `options` array in METIS manual
+
+use metis_sys::*;
+
+/// METIS routine which takes an `options` array
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Routine {
+    PartGraphRecursive,
+    PartGraphKway,
+    NodeND,
+    NodeNDP,
+    ComputeVertexSeparator,
+    PartMeshDual,
+    PartMeshNodal,
+}
+
+/// Partitioning method, `METIS_OPTION_PTYPE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitioningType {
+    /// Multilevel recursive bisectioning
+    RecursiveBisection,
+    /// Multilevel k-way partitioning
+    Kway,
+}
+
+/// Objective to be minimized, `METIS_OPTION_OBJTYPE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectiveType {
+    /// Edge-cut minimization
+    EdgeCut,
+    /// Total communication volume minimization
+    CommunicationVolume,
+}
+
+/// Matching scheme used during coarsening, `METIS_OPTION_CTYPE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoarseningType {
+    /// Random matching
+    RandomMatching,
+    /// Sorted heavy-edge matching
+    SortedHeavyEdgeMatching,
+}
+
+/// Algorithm used during initial partitioning, `METIS_OPTION_IPTYPE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitialPartitioningType {
+    /// Grows a bisection using a greedy strategy
+    Grow,
+    /// Computes a bisection at random followed by a refinement
+    Random,
+    /// Derives a separator from an edge cut
+    Edge,
+    /// Grows a bisection using a greedy node-based strategy
+    Node,
+}
+
+/// Algorithm used for refinement, `METIS_OPTION_RTYPE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefinementType {
+    /// FM-based cut refinement
+    FiducciaMattheyses,
+    /// Greedy-based cut and volume refinement
+    Greedy,
+    /// Two-sided node FM refinement
+    Sep2Sided,
+    /// One-sided node FM refinement
+    Sep1Sided,
+}
+
+/// Numbering scheme of the index arrays of a graph, `METIS_OPTION_NUMBERING`
+///
+/// This is not a part of [Options] since it is determined by the graph passed to METIS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Numbering {
+    /// C-style numbering which is assumed to start from 0
+    #[default]
+    C,
+    /// Fortran-style numbering which is assumed to start from 1
+    Fortran,
+}
+
+impl Numbering {
+    /// Index of the first element, and the value of `METIS_OPTION_NUMBERING`
+    pub fn offset(self) -> idx_t {
+        match self {
+            Numbering::C => 0,
+            Numbering::Fortran => 1,
+        }
+    }
+}
+
+/// Error for invalid options
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
+pub enum OptionError {
+    #[error("Option {option} cannot be used for {routine:?}")]
+    NotApplicable {
+        option: &'static str,
+        routine: Routine,
+    },
+
+    #[error("Option {option} = {value} cannot be used for {routine:?}")]
+    InvalidChoice {
+        option: &'static str,
+        value: String,
+        routine: Routine,
+    },
+
+    #[error("Option {option} must be positive: {value}")]
+    NonPositive { option: &'static str, value: i32 },
+
+    #[error("Option {option} must be non-negative: {value}")]
+    Negative { option: &'static str, value: i32 },
+}
+
+/// Builder of the `options` array
+///
+/// Options which are not set are left to the METIS default value.
+///
+/// ```
+/// use metis::options::*;
+///
+/// let options = Options::default()
+///     .ctype(CoarseningType::RandomMatching)
+///     .niter(20)
+///     .seed(42);
+/// let raw = options.to_raw(Routine::PartGraphKway).unwrap();
+/// assert_eq!(raw[metis_sys::moptions_et::METIS_OPTION_NITER as usize], 20);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    ptype: Option<PartitioningType>,
+    objtype: Option<ObjectiveType>,
+    ctype: Option<CoarseningType>,
+    iptype: Option<InitialPartitioningType>,
+    rtype: Option<RefinementType>,
+    niter: Option<i32>,
+    ncuts: Option<i32>,
+    seed: Option<i32>,
+    ufactor: Option<i32>,
+    minconn: Option<bool>,
+    contig: Option<bool>,
+    compress: Option<bool>,
+    ccorder: Option<bool>,
+    pfactor: Option<i32>,
+    nseps: Option<i32>,
+}
+
+impl Options {
+    /// Partitioning method
+    pub fn ptype(mut self, ptype: PartitioningType) -> Self {
+        self.ptype = Some(ptype);
+        self
+    }
+
+    /// Objective to be minimized
+    pub fn objtype(mut self, objtype: ObjectiveType) -> Self {
+        self.objtype = Some(objtype);
+        self
+    }
+
+    /// Matching scheme used during coarsening
+    pub fn ctype(mut self, ctype: CoarseningType) -> Self {
+        self.ctype = Some(ctype);
+        self
+    }
+
+    /// Algorithm used during initial partitioning
+    pub fn iptype(mut self, iptype: InitialPartitioningType) -> Self {
+        self.iptype = Some(iptype);
+        self
+    }
+
+    /// Algorithm used for refinement
+    pub fn rtype(mut self, rtype: RefinementType) -> Self {
+        self.rtype = Some(rtype);
+        self
+    }
+
+    /// Number of iterations for the refinement algorithms at each stage of the uncoarsening
+    pub fn niter(mut self, niter: i32) -> Self {
+        self.niter = Some(niter);
+        self
+    }
+
+    /// Number of different partitionings to compute. The final one is the one with the best objective.
+    pub fn ncuts(mut self, ncuts: i32) -> Self {
+        self.ncuts = Some(ncuts);
+        self
+    }
+
+    /// Seed for the random number generator
+    pub fn seed(mut self, seed: i32) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Maximum allowed load imbalance, `1 + ufactor / 1000`
+    pub fn ufactor(mut self, ufactor: i32) -> Self {
+        self.ufactor = Some(ufactor);
+        self
+    }
+
+    /// Explicitly minimize the maximum connectivity
+    pub fn minconn(mut self, minconn: bool) -> Self {
+        self.minconn = Some(minconn);
+        self
+    }
+
+    /// Force contiguous partitions
+    pub fn contig(mut self, contig: bool) -> Self {
+        self.contig = Some(contig);
+        self
+    }
+
+    /// Compress the graph by removing vertices with identical adjacency lists before ordering
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.compress = Some(compress);
+        self
+    }
+
+    /// Detect and order connected components separately
+    pub fn ccorder(mut self, ccorder: bool) -> Self {
+        self.ccorder = Some(ccorder);
+        self
+    }
+
+    /// Prune vertices with degree greater than `0.1 * pfactor` times the average degree before ordering
+    pub fn pfactor(mut self, pfactor: i32) -> Self {
+        self.pfactor = Some(pfactor);
+        self
+    }
+
+    /// Number of different separators computed at each level of nested dissection
+    pub fn nseps(mut self, nseps: i32) -> Self {
+        self.nseps = Some(nseps);
+        self
+    }
+
+    /// Validate options for `routine`, and create `options` array
+    pub fn to_raw(&self, routine: Routine) -> Result<[idx_t; METIS_NOPTIONS], OptionError> {
+        self.check(routine)?;
+
+        let mut options = [0; METIS_NOPTIONS];
+        unsafe { METIS_SetDefaultOptions(options.as_mut_ptr()) };
+
+        let mut set = |option: moptions_et, value: Option<idx_t>| {
+            if let Some(value) = value {
+                options[option as usize] = value;
+            }
+        };
+        set(
+            moptions_et::METIS_OPTION_PTYPE,
+            self.ptype.map(|ptype| match ptype {
+                PartitioningType::RecursiveBisection => mptype_et::METIS_PTYPE_RB as idx_t,
+                PartitioningType::Kway => mptype_et::METIS_PTYPE_KWAY as idx_t,
+            }),
+        );
+        set(
+            moptions_et::METIS_OPTION_OBJTYPE,
+            self.objtype.map(|objtype| match objtype {
+                ObjectiveType::EdgeCut => mobjtype_et::METIS_OBJTYPE_CUT as idx_t,
+                ObjectiveType::CommunicationVolume => mobjtype_et::METIS_OBJTYPE_VOL as idx_t,
+            }),
+        );
+        set(
+            moptions_et::METIS_OPTION_CTYPE,
+            self.ctype.map(|ctype| match ctype {
+                CoarseningType::RandomMatching => mctype_et::METIS_CTYPE_RM as idx_t,
+                CoarseningType::SortedHeavyEdgeMatching => mctype_et::METIS_CTYPE_SHEM as idx_t,
+            }),
+        );
+        set(
+            moptions_et::METIS_OPTION_IPTYPE,
+            self.iptype.map(|iptype| match iptype {
+                InitialPartitioningType::Grow => miptype_et::METIS_IPTYPE_GROW as idx_t,
+                InitialPartitioningType::Random => miptype_et::METIS_IPTYPE_RANDOM as idx_t,
+                InitialPartitioningType::Edge => miptype_et::METIS_IPTYPE_EDGE as idx_t,
+                InitialPartitioningType::Node => miptype_et::METIS_IPTYPE_NODE as idx_t,
+            }),
+        );
+        set(
+            moptions_et::METIS_OPTION_RTYPE,
+            self.rtype.map(|rtype| match rtype {
+                RefinementType::FiducciaMattheyses => mrtype_et::METIS_RTYPE_FM as idx_t,
+                RefinementType::Greedy => mrtype_et::METIS_RTYPE_GREEDY as idx_t,
+                RefinementType::Sep2Sided => mrtype_et::METIS_RTYPE_SEP2SIDED as idx_t,
+                RefinementType::Sep1Sided => mrtype_et::METIS_RTYPE_SEP1SIDED as idx_t,
+            }),
+        );
+        set(moptions_et::METIS_OPTION_NITER, self.niter);
+        set(moptions_et::METIS_OPTION_NCUTS, self.ncuts);
+        set(moptions_et::METIS_OPTION_SEED, self.seed);
+        set(moptions_et::METIS_OPTION_UFACTOR, self.ufactor);
+        set(
+            moptions_et::METIS_OPTION_MINCONN,
+            self.minconn.map(idx_t::from),
+        );
+        set(
+            moptions_et::METIS_OPTION_CONTIG,
+            self.contig.map(idx_t::from),
+        );
+        set(
+            moptions_et::METIS_OPTION_COMPRESS,
+            self.compress.map(idx_t::from),
+        );
+        set(
+            moptions_et::METIS_OPTION_CCORDER,
+            self.ccorder.map(idx_t::from),
+        );
+        set(moptions_et::METIS_OPTION_PFACTOR, self.pfactor);
+        set(moptions_et::METIS_OPTION_NSEPS, self.nseps);
+        Ok(options)
+    }
+
+    /// Names of options explicitly set
+    fn set_options(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        let mut push = |name, is_set: bool| {
+            if is_set {
+                names.push(name);
+            }
+        };
+        push("ptype", self.ptype.is_some());
+        push("objtype", self.objtype.is_some());
+        push("ctype", self.ctype.is_some());
+        push("iptype", self.iptype.is_some());
+        push("rtype", self.rtype.is_some());
+        push("niter", self.niter.is_some());
+        push("ncuts", self.ncuts.is_some());
+        push("seed", self.seed.is_some());
+        push("ufactor", self.ufactor.is_some());
+        push("minconn", self.minconn.is_some());
+        push("contig", self.contig.is_some());
+        push("compress", self.compress.is_some());
+        push("ccorder", self.ccorder.is_some());
+        push("pfactor", self.pfactor.is_some());
+        push("nseps", self.nseps.is_some());
+        names
+    }
+
+    fn check(&self, routine: Routine) -> Result<(), OptionError> {
+        // Mesh partitioning routines partition the dual or nodal graph
+        // by the graph partitioning routine selected by `ptype`
+        if let Routine::PartMeshDual | Routine::PartMeshNodal = routine {
+            let graph_routine = match self.ptype {
+                Some(PartitioningType::RecursiveBisection) => Routine::PartGraphRecursive,
+                _ => Routine::PartGraphKway,
+            };
+            let graph_options = Options {
+                ptype: None,
+                ..self.clone()
+            };
+            return graph_options.check(graph_routine).map_err(|e| match e {
+                OptionError::NotApplicable { option, .. } => {
+                    OptionError::NotApplicable { option, routine }
+                }
+                OptionError::InvalidChoice { option, value, .. } => OptionError::InvalidChoice {
+                    option,
+                    value,
+                    routine,
+                },
+                e => e,
+            });
+        }
+
+        let not_applicable = |option| Err(OptionError::NotApplicable { option, routine });
+        let invalid_choice = |option, value: &dyn std::fmt::Debug| {
+            Err(OptionError::InvalidChoice {
+                option,
+                value: format!("{:?}", value),
+                routine,
+            })
+        };
+
+        // See `SetupCtrl` and `CheckParams` in libmetis/options.c
+        let applicable: &[&str] = match routine {
+            Routine::PartGraphRecursive => &[
+                "objtype", "ctype", "iptype", "niter", "ncuts", "seed", "ufactor",
+            ],
+            Routine::PartGraphKway => &[
+                "objtype", "ctype", "niter", "ncuts", "seed", "ufactor", "minconn", "contig",
+            ],
+            Routine::NodeND => &[
+                "ctype", "iptype", "rtype", "niter", "seed", "ufactor", "compress", "ccorder",
+                "pfactor", "nseps",
+            ],
+            // `METIS_NodeNDP` does not read `ccorder` and `pfactor`
+            Routine::NodeNDP => &[
+                "ctype", "iptype", "rtype", "niter", "seed", "ufactor", "compress", "nseps",
+            ],
+            Routine::ComputeVertexSeparator => &[
+                "ctype", "iptype", "rtype", "niter", "seed", "ufactor", "nseps",
+            ],
+            Routine::PartMeshDual | Routine::PartMeshNodal => unreachable!(),
+        };
+        let is_ordering = matches!(
+            routine,
+            Routine::NodeND | Routine::NodeNDP | Routine::ComputeVertexSeparator
+        );
+        for option in self.set_options() {
+            if !applicable.contains(&option) {
+                return not_applicable(option);
+            }
+        }
+
+        if let Some(objtype) = self.objtype {
+            if routine == Routine::PartGraphRecursive && objtype != ObjectiveType::EdgeCut {
+                return invalid_choice("objtype", &objtype);
+            }
+        }
+        if let Some(iptype) = self.iptype {
+            use InitialPartitioningType::*;
+            match (routine, iptype) {
+                (Routine::PartGraphRecursive, Grow) | (Routine::PartGraphRecursive, Random) => {}
+                _ if is_ordering && (iptype == Edge || iptype == Node) => {}
+                _ => return invalid_choice("iptype", &iptype),
+            }
+        }
+        if let Some(rtype) = self.rtype {
+            use RefinementType::*;
+            if !(is_ordering && (rtype == Sep1Sided || rtype == Sep2Sided)) {
+                return invalid_choice("rtype", &rtype);
+            }
+        }
+
+        for &(option, value) in &[
+            ("niter", self.niter),
+            ("ncuts", self.ncuts),
+            ("ufactor", self.ufactor),
+            ("nseps", self.nseps),
+        ] {
+            if let Some(value) = value {
+                if value <= 0 {
+                    return Err(OptionError::NonPositive { option, value });
+                }
+            }
+        }
+        if let Some(pfactor) = self.pfactor {
+            if pfactor < 0 {
+                return Err(OptionError::Negative {
+                    option: "pfactor",
+                    value: pfactor,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default() {
+        let raw = Options::default().to_raw(Routine::PartGraphKway).unwrap();
+        assert!(raw.iter().all(|&v| v == -1));
+    }
+
+    #[test]
+    fn set() {
+        let raw = Options::default()
+            .objtype(ObjectiveType::CommunicationVolume)
+            .ncuts(3)
+            .contig(true)
+            .to_raw(Routine::PartGraphKway)
+            .unwrap();
+        assert_eq!(
+            raw[moptions_et::METIS_OPTION_OBJTYPE as usize],
+            mobjtype_et::METIS_OBJTYPE_VOL as idx_t
+        );
+        assert_eq!(raw[moptions_et::METIS_OPTION_NCUTS as usize], 3);
+        assert_eq!(raw[moptions_et::METIS_OPTION_CONTIG as usize], 1);
+        assert_eq!(raw[moptions_et::METIS_OPTION_NITER as usize], -1);
+    }
+
+    #[test]
+    fn not_applicable() {
+        let err = Options::default()
+            .contig(true)
+            .to_raw(Routine::PartGraphRecursive)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            OptionError::NotApplicable {
+                option: "contig",
+                routine: Routine::PartGraphRecursive
+            }
+        );
+        assert!(Options::default()
+            .compress(true)
+            .to_raw(Routine::PartGraphKway)
+            .is_err());
+    }
+
+    #[test]
+    fn invalid_choice() {
+        let err = Options::default()
+            .objtype(ObjectiveType::CommunicationVolume)
+            .to_raw(Routine::PartGraphRecursive)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            OptionError::InvalidChoice {
+                option: "objtype",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn node_nd() {
+        let raw = Options::default()
+            .compress(false)
+            .ccorder(true)
+            .pfactor(100)
+            .nseps(2)
+            .rtype(RefinementType::Sep2Sided)
+            .to_raw(Routine::NodeND)
+            .unwrap();
+        assert_eq!(raw[moptions_et::METIS_OPTION_COMPRESS as usize], 0);
+        assert_eq!(raw[moptions_et::METIS_OPTION_CCORDER as usize], 1);
+        assert_eq!(raw[moptions_et::METIS_OPTION_PFACTOR as usize], 100);
+        assert_eq!(raw[moptions_et::METIS_OPTION_NSEPS as usize], 2);
+
+        assert!(Options::default()
+            .rtype(RefinementType::Greedy)
+            .to_raw(Routine::NodeND)
+            .is_err());
+        assert!(Options::default().ncuts(2).to_raw(Routine::NodeND).is_err());
+    }
+
+    #[test]
+    fn mesh() {
+        let options = Options::default()
+            .ptype(PartitioningType::RecursiveBisection)
+            .iptype(InitialPartitioningType::Random);
+        let raw = options.to_raw(Routine::PartMeshDual).unwrap();
+        assert_eq!(
+            raw[moptions_et::METIS_OPTION_PTYPE as usize],
+            mptype_et::METIS_PTYPE_RB as idx_t
+        );
+
+        // k-way is the default
+        let options = Options::default().iptype(InitialPartitioningType::Random);
+        assert_eq!(
+            options.to_raw(Routine::PartMeshNodal).unwrap_err(),
+            OptionError::NotApplicable {
+                option: "iptype",
+                routine: Routine::PartMeshNodal
+            }
+        );
+    }
+
+    #[test]
+    fn non_positive() {
+        let err = Options::default()
+            .niter(0)
+            .to_raw(Routine::PartGraphKway)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            OptionError::NonPositive {
+                option: "niter",
+                value: 0
+            }
+        );
+    }
+}
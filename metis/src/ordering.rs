@@ -0,0 +1,414 @@
+//! Fill-reducing orderings of sparse matrices
+
+use metis_sys::*;
+use std::{ops::Range, ptr::null_mut};
+
+use crate::{error::*, graph::CSRGraph, options::*, partition::as_mut_ptr_or_null};
+
+/// Error for invalid permutation
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
+pub enum PermutationError {
+    #[error("Permutation index is out-of-range: perm[{position}] = {index} (length = {len})")]
+    OutOfRange {
+        position: usize,
+        index: i32,
+        len: usize,
+    },
+
+    #[error("Index {index} appears more than once in permutation")]
+    Duplicated { index: i32 },
+}
+
+/// Permutation of vertices and its inverse
+///
+/// Row (column) `i` of the permuted matrix is the `perm[i]` row (column) of the original matrix,
+/// and row (column) `i` of the original matrix is the `iperm[i]` row (column) of the permuted matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Permutation {
+    perm: Vec<i32>,
+    iperm: Vec<i32>,
+}
+
+impl Permutation {
+    /// Create from `perm`, and check it is a bijection on `0..perm.len()`
+    pub fn from_perm(perm: Vec<i32>) -> std::result::Result<Self, PermutationError> {
+        let len = perm.len();
+        let mut iperm = vec![-1; len];
+        for (position, &index) in perm.iter().enumerate() {
+            if index < 0 || index as usize >= len {
+                return Err(PermutationError::OutOfRange {
+                    position,
+                    index,
+                    len,
+                });
+            }
+            if iperm[index as usize] != -1 {
+                return Err(PermutationError::Duplicated { index });
+            }
+            iperm[index as usize] = position as i32;
+        }
+        Ok(Permutation { perm, iperm })
+    }
+
+    /// Create from `iperm`, and check it is a bijection on `0..iperm.len()`
+    pub fn from_iperm(iperm: Vec<i32>) -> std::result::Result<Self, PermutationError> {
+        let inv = Self::from_perm(iperm)?;
+        Ok(Permutation {
+            perm: inv.iperm,
+            iperm: inv.perm,
+        })
+    }
+
+    /// `perm` in METIS manual
+    pub fn perm(&self) -> &[i32] {
+        &self.perm
+    }
+
+    /// `iperm` in METIS manual
+    pub fn iperm(&self) -> &[i32] {
+        &self.iperm
+    }
+
+    pub fn len(&self) -> usize {
+        self.perm.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.perm.is_empty()
+    }
+}
+
+/// Compute fill-reducing ordering using multilevel nested dissection
+///
+/// Vertex weights of `graph` are used if exist, and must be a single weight for each vertex.
+pub fn node_nd(graph: &CSRGraph) -> Result<Permutation> {
+    node_nd_with_options(graph, &Options::default())
+}
+
+/// Compute fill-reducing ordering using multilevel nested dissection with options
+///
+/// `compress`, `ccorder`, `pfactor`, `nseps` and `ufactor` of [Options] are specific to ordering.
+pub fn node_nd_with_options(graph: &CSRGraph, options: &Options) -> Result<Permutation> {
+    let mut options = options.to_raw(Routine::NodeND)?;
+
+    // METIS does not take `const` pointers, so pass copies of the inputs
+    let numbering = graph.numbering();
+    options[moptions_et::METIS_OPTION_NUMBERING as usize] = numbering.offset();
+    let (mut xadj, mut adjncy) = graph.to_raw_arrays(numbering);
+    let mut vwgt = single_vertex_weights(graph, "METIS_NodeND")?;
+    let mut nvtxs = graph.num_vertices() as idx_t;
+
+    let mut perm = vec![0; graph.num_vertices()];
+    let mut iperm = vec![0; graph.num_vertices()];
+    unsafe {
+        METIS_NodeND(
+            &mut nvtxs,
+            xadj.as_mut_ptr(),
+            adjncy.as_mut_ptr(),
+            as_mut_ptr_or_null(&mut vwgt),
+            options.as_mut_ptr(),
+            perm.as_mut_ptr(),
+            iperm.as_mut_ptr(),
+        )
+    }
+    .check("METIS_NodeND")?;
+    for i in perm.iter_mut().chain(iperm.iter_mut()) {
+        *i -= numbering.offset();
+    }
+
+    Ok(Permutation::from_perm(perm)?)
+}
+
+/// Validate `graph` for `api_name`, and copy its vertex weights which must be a single weight for each vertex
+pub(crate) fn single_vertex_weights(
+    graph: &CSRGraph,
+    api_name: &str,
+) -> Result<Option<Vec<idx_t>>> {
+    graph.check_input(api_name)?;
+    let vwgt = match graph.vwgt() {
+        Some(vwgt) => vwgt,
+        None => return Ok(None),
+    };
+    if graph.ncon() != 1 {
+        return Err(Error::invalid_input(
+            api_name,
+            format!(
+                "Only a single vertex weight is supported, but ncon = {}",
+                graph.ncon()
+            ),
+        ));
+    }
+    Ok(Some(vwgt.to_vec()))
+}
+
+/// Separator tree of the top levels of nested dissection
+///
+/// Vertices are numbered in the new ordering as left subtree, right subtree, and then separator,
+/// recursively. Subtrees in the same level do not share any edge, and can be factorized independently.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeparatorTree {
+    /// Ranges of leaf subdomains from left to right
+    pub subdomains: Vec<Range<usize>>,
+    /// Ranges of separators in each level from left to right. `separators[0]` is the top-level separator.
+    pub separators: Vec<Vec<Range<usize>>>,
+}
+
+impl SeparatorTree {
+    /// Create from `sizes` array of `METIS_NodeNDP`
+    ///
+    /// `sizes` has `2 * npes - 1` elements; sizes of `npes` subdomains,
+    /// and then sizes of separators from the bottom level to the top.
+    pub(crate) fn from_sizes(sizes: &[i32]) -> Self {
+        let npes = sizes.len() / 2 + 1;
+        assert!(
+            npes.is_power_of_two() && sizes.len() == 2 * npes - 1,
+            "sizes must have 2 * npes - 1 elements where npes is a power of 2"
+        );
+        let num_levels = npes.trailing_zeros() as usize;
+
+        let (leaves, mut rest) = sizes.split_at(npes);
+        let mut separator_sizes = vec![Vec::new(); num_levels];
+        for level in (0..num_levels).rev() {
+            let (seps, r) = rest.split_at(1 << level);
+            separator_sizes[level] = seps.to_vec();
+            rest = r;
+        }
+
+        let mut tree = SeparatorTree {
+            subdomains: vec![0..0; npes],
+            separators: separator_sizes
+                .iter()
+                .map(|seps| vec![0..0; seps.len()])
+                .collect(),
+        };
+        tree.fill(leaves, &separator_sizes, 0, 0, 0);
+        tree
+    }
+
+    /// Fill the ranges of subtree `(level, index)` starting at `start`, and returns its end
+    fn fill(
+        &mut self,
+        leaves: &[i32],
+        separator_sizes: &[Vec<i32>],
+        level: usize,
+        index: usize,
+        start: usize,
+    ) -> usize {
+        if level == separator_sizes.len() {
+            let end = start + leaves[index] as usize;
+            self.subdomains[index] = start..end;
+            return end;
+        }
+        let mid = self.fill(leaves, separator_sizes, level + 1, 2 * index, start);
+        let sep_start = self.fill(leaves, separator_sizes, level + 1, 2 * index + 1, mid);
+        let end = sep_start + separator_sizes[level][index] as usize;
+        self.separators[level][index] = sep_start..end;
+        end
+    }
+
+    /// Number of levels of dissection, `log2(npes)`
+    pub fn num_levels(&self) -> usize {
+        self.separators.len()
+    }
+}
+
+/// Compute fill-reducing ordering with at least `log2(npes)` levels of nested dissection,
+/// and returns the separator tree of the top `log2(npes)` levels
+///
+/// `npes` must be a power of 2, and at least 2 since METIS does not fill `sizes` for `npes == 1`.
+/// Vertex weights are not supported since METIS reports the total weight of each subtree
+/// instead of the number of its vertices.
+pub fn node_ndp(graph: &CSRGraph, npes: usize) -> Result<(Permutation, SeparatorTree)> {
+    node_ndp_with_options(graph, npes, &Options::default())
+}
+
+/// [node_ndp] with options
+pub fn node_ndp_with_options(
+    graph: &CSRGraph,
+    npes: usize,
+    options: &Options,
+) -> Result<(Permutation, SeparatorTree)> {
+    let api_name = "METIS_NodeNDP";
+    if !npes.is_power_of_two() {
+        return Err(Error::invalid_input(
+            api_name,
+            format!("npes must be a power of 2, but is {}", npes),
+        ));
+    }
+    if npes < 2 {
+        return Err(Error::invalid_input(
+            api_name,
+            "npes must be at least 2, use node_nd for an ordering without separator tree",
+        ));
+    }
+    let mut options = options.to_raw(Routine::NodeNDP)?;
+
+    // METIS does not take `const` pointers, so pass copies of the inputs
+    // `METIS_NodeNDP` does not support `METIS_OPTION_NUMBERING`
+    let (mut xadj, mut adjncy) = graph.to_raw_arrays(Numbering::C);
+    graph.check_input(api_name)?;
+    if graph.vwgt().is_some() {
+        return Err(Error::invalid_input(
+            api_name,
+            "Vertex weights are not supported since sizes would be weights instead of vertex counts",
+        ));
+    }
+
+    let mut perm = vec![0; graph.num_vertices()];
+    let mut iperm = vec![0; graph.num_vertices()];
+    let mut sizes = vec![0; 2 * npes - 1];
+    unsafe {
+        METIS_NodeNDP(
+            graph.num_vertices() as idx_t,
+            xadj.as_mut_ptr(),
+            adjncy.as_mut_ptr(),
+            null_mut(),
+            npes as idx_t,
+            options.as_mut_ptr(),
+            perm.as_mut_ptr(),
+            iperm.as_mut_ptr(),
+            sizes.as_mut_ptr(),
+        )
+    }
+    .check(api_name)?;
+
+    Ok((
+        Permutation::from_perm(perm)?,
+        SeparatorTree::from_sizes(&sizes),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::graph::*;
+
+    #[test]
+    fn permutation() {
+        let p = Permutation::from_perm(vec![2, 0, 1]).unwrap();
+        assert_eq!(p.iperm(), &[1, 2, 0]);
+        assert_eq!(Permutation::from_iperm(vec![1, 2, 0]).unwrap(), p);
+
+        assert_eq!(
+            Permutation::from_perm(vec![0, 3, 1]).unwrap_err(),
+            PermutationError::OutOfRange {
+                position: 1,
+                index: 3,
+                len: 3
+            }
+        );
+        assert_eq!(
+            Permutation::from_perm(vec![0, 1, 1]).unwrap_err(),
+            PermutationError::Duplicated { index: 1 }
+        );
+    }
+
+    #[test]
+    fn node_nd_grid() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let p = node_nd(&graph).unwrap();
+        assert_eq!(p.len(), 15);
+        for i in 0..15 {
+            assert_eq!(p.iperm()[p.perm()[i] as usize], i as i32);
+        }
+    }
+
+    #[test]
+    fn node_nd_fortran() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let p = node_nd(&graph).unwrap();
+        let fortran = node_nd(&graph.clone().with_numbering(Numbering::Fortran)).unwrap();
+        assert_eq!(p, fortran);
+
+        let (p, _) = node_ndp(&graph, 2).unwrap();
+        let (fortran, _) = node_ndp(&graph.with_numbering(Numbering::Fortran), 2).unwrap();
+        assert_eq!(p, fortran);
+    }
+
+    #[test]
+    fn node_nd_pattern() {
+        // 1D Laplacian with diagonal entries
+        let row_ptr = [0, 2, 5, 8, 10];
+        let col_ind = [0, 1, 0, 1, 2, 1, 2, 3, 2, 3];
+        let graph = CSRGraph::from_symmetric_pattern(&row_ptr, &col_ind).unwrap();
+        assert_eq!(graph.xadj(), &[0, 1, 3, 5, 6]);
+        assert_eq!(graph.adjncy(), &[1, 0, 2, 1, 3, 2]);
+
+        let options = Options::default().compress(false).ccorder(true);
+        let p = node_nd_with_options(&graph, &options).unwrap();
+        assert_eq!(p.len(), 4);
+
+        for (row_ptr, col_ind) in [
+            (&[][..], &[][..]),
+            (&[1, 2], &[0, 0]),
+            (&[0, 2, 1], &[0, 1]),
+            (&[0, 1, 3], &[0, 1]),
+            (&[0, 1, 2], &[0, 2]),
+            (&[0, 1, 2], &[-1, 1]),
+        ] {
+            let err = CSRGraph::from_symmetric_pattern(row_ptr, col_ind).unwrap_err();
+            assert!(matches!(err, Error::InvalidInput { .. }));
+        }
+    }
+
+    #[test]
+    fn separator_tree() {
+        // [LL, LR, RL, RR, sepL, sepR, sepRoot]
+        let tree = SeparatorTree::from_sizes(&[4, 3, 5, 2, 1, 2, 3]);
+        assert_eq!(tree.num_levels(), 2);
+        assert_eq!(tree.subdomains, vec![0..4, 4..7, 8..13, 13..15]);
+        assert_eq!(tree.separators, vec![vec![17..20], vec![7..8, 15..17]]);
+
+        let tree = SeparatorTree::from_sizes(&[10]);
+        assert_eq!(tree.num_levels(), 0);
+        assert_eq!(tree.subdomains, vec![0..10]);
+    }
+
+    #[test]
+    fn node_ndp_grid() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let (p, tree) = node_ndp(&graph, 2).unwrap();
+        assert_eq!(p.len(), 15);
+        assert_eq!(tree.num_levels(), 1);
+        assert_eq!(tree.separators[0][0].end, 15);
+
+        // Two subdomains must not be connected
+        let left = tree.subdomains[0].clone();
+        let right = tree.subdomains[1].clone();
+        for new in left {
+            let old = p.perm()[new] as usize;
+            for &nbr in &graph.adjncy()[graph.xadj()[old] as usize..graph.xadj()[old + 1] as usize]
+            {
+                assert!(!right.contains(&(p.iperm()[nbr as usize] as usize)));
+            }
+        }
+
+        let err = node_ndp(&graph, 3).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid input for METIS routine (METIS_NodeNDP): npes must be a power of 2, but is 3"
+        );
+        let err = node_ndp(&graph, 1).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }));
+    }
+
+    #[test]
+    fn node_ndp_weighted() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A)
+            .unwrap()
+            .with_vertex_weights(1, vec![100; 15])
+            .unwrap();
+        let err = node_ndp(&graph, 2).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }));
+    }
+
+    #[test]
+    fn multiple_vertex_weights() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A)
+            .unwrap()
+            .with_vertex_weights(2, vec![1; 30])
+            .unwrap();
+        let err = node_nd(&graph).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }));
+    }
+}
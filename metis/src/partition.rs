@@ -0,0 +1,610 @@
+//! Graph and mesh partitioning
+
+use metis_sys::*;
+use std::ptr::null_mut;
+
+use crate::{
+    error::*,
+    graph::CSRGraph,
+    io::partition::TOLERANCE,
+    mesh::{check_ncommon, Mesh},
+    options::*,
+};
+
+/// Result of graph partitioning
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partition {
+    /// Number of parts
+    pub num_parts: usize,
+    /// Part index of each vertex, `part` in METIS manual
+    pub part: Vec<i32>,
+    /// Edge-cut or total communication volume of the partitioning, `objval` in METIS manual
+    pub objval: i32,
+}
+
+/// Partition a graph into `nparts` parts using multilevel k-way partitioning
+pub fn partition_kway(graph: &CSRGraph, nparts: usize) -> Result<Partition> {
+    partition_kway_with_options(graph, nparts, None, None, &Options::default())
+}
+
+/// Partition a graph into `nparts` parts using multilevel k-way partitioning with options
+///
+/// See [partition_recursive] for `tpwgts` and `ubvec`.
+pub fn partition_kway_with_options(
+    graph: &CSRGraph,
+    nparts: usize,
+    tpwgts: Option<&[f32]>,
+    ubvec: Option<&[f32]>,
+    options: &Options,
+) -> Result<Partition> {
+    part_graph(
+        Routine::PartGraphKway,
+        graph,
+        nparts,
+        tpwgts,
+        ubvec,
+        options,
+    )
+}
+
+/// Partition a graph into `nparts` parts using multilevel recursive bisection
+///
+/// - `tpwgts` is the desired weight of each part, and must have `nparts` elements summing to 1.
+///   For multi-constraint graph, it must have `nparts * ncon` elements.
+///   Parts are equally weighted if `None`.
+/// - `ubvec` is the allowed load imbalance tolerance, e.g. `1.05` allows 5% imbalance.
+///   It must have one element larger than 1 for each vertex weight.
+///   METIS default (`1.001` for single constraint) is used if `None`.
+///
+/// Vertex weights, vertex sizes and edge weights of `graph` are used if exist.
+pub fn partition_recursive(
+    graph: &CSRGraph,
+    nparts: usize,
+    tpwgts: Option<&[f32]>,
+    ubvec: Option<&[f32]>,
+) -> Result<Partition> {
+    partition_recursive_with_options(graph, nparts, tpwgts, ubvec, &Options::default())
+}
+
+/// Partition a graph into `nparts` parts using multilevel recursive bisection with options
+pub fn partition_recursive_with_options(
+    graph: &CSRGraph,
+    nparts: usize,
+    tpwgts: Option<&[f32]>,
+    ubvec: Option<&[f32]>,
+    options: &Options,
+) -> Result<Partition> {
+    part_graph(
+        Routine::PartGraphRecursive,
+        graph,
+        nparts,
+        tpwgts,
+        ubvec,
+        options,
+    )
+}
+
+/// Common signature of `METIS_PartGraphKway` and `METIS_PartGraphRecursive`
+type PartGraphFn = unsafe extern "C" fn(
+    *mut idx_t,
+    *mut idx_t,
+    *mut idx_t,
+    *mut idx_t,
+    *mut idx_t,
+    *mut idx_t,
+    *mut idx_t,
+    *mut idx_t,
+    *mut real_t,
+    *mut real_t,
+    *mut idx_t,
+    *mut idx_t,
+    *mut idx_t,
+) -> ::std::os::raw::c_int;
+
+fn part_graph(
+    routine: Routine,
+    graph: &CSRGraph,
+    nparts: usize,
+    tpwgts: Option<&[f32]>,
+    ubvec: Option<&[f32]>,
+    options: &Options,
+) -> Result<Partition> {
+    let (api, api_name): (PartGraphFn, _) = match routine {
+        Routine::PartGraphKway => (METIS_PartGraphKway, "METIS_PartGraphKway"),
+        Routine::PartGraphRecursive => (METIS_PartGraphRecursive, "METIS_PartGraphRecursive"),
+        _ => unreachable!("{:?} is not a graph partitioning routine", routine),
+    };
+    let mut options = options.to_raw(routine)?;
+
+    let ncon = graph.ncon();
+    graph.check_input(api_name)?;
+    check_nparts(api_name, nparts)?;
+    if let Some(tpwgts) = tpwgts {
+        check_tpwgts(api_name, tpwgts, nparts, ncon)?;
+    }
+    if let Some(ubvec) = ubvec {
+        check_ubvec(api_name, ubvec, ncon)?;
+    }
+
+    // METIS does not take `const` pointers, so pass copies of the inputs
+    let numbering = graph.numbering();
+    options[moptions_et::METIS_OPTION_NUMBERING as usize] = numbering.offset();
+    let (mut xadj, mut adjncy) = graph.to_raw_arrays(numbering);
+    let mut vwgt = graph.vwgt().map(|v| v.to_vec());
+    let mut vsize = graph.vsize().map(|v| v.to_vec());
+    let mut adjwgt = graph.adjwgt().map(|a| a.to_vec());
+    let mut tpwgts = tpwgts.map(|t| t.to_vec());
+    let mut ubvec = ubvec.map(|u| u.to_vec());
+    let mut nvtxs = graph.num_vertices() as idx_t;
+    let mut ncon = ncon as idx_t;
+    let mut nparts_ = nparts as idx_t;
+
+    let mut objval = 0;
+    let mut part = vec![0; graph.num_vertices()];
+    unsafe {
+        api(
+            &mut nvtxs,
+            &mut ncon,
+            xadj.as_mut_ptr(),
+            adjncy.as_mut_ptr(),
+            as_mut_ptr_or_null(&mut vwgt),
+            as_mut_ptr_or_null(&mut vsize),
+            as_mut_ptr_or_null(&mut adjwgt),
+            &mut nparts_,
+            as_mut_ptr_or_null(&mut tpwgts),
+            as_mut_ptr_or_null(&mut ubvec),
+            options.as_mut_ptr(),
+            &mut objval,
+            part.as_mut_ptr(),
+        )
+    }
+    .check(api_name)?;
+    for p in &mut part {
+        *p -= numbering.offset();
+    }
+
+    Ok(Partition {
+        num_parts: nparts,
+        part,
+        objval,
+    })
+}
+
+/// Result of mesh partitioning
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshPartition {
+    /// Number of parts
+    pub num_parts: usize,
+    /// Part index of each element, `epart` in METIS manual
+    pub epart: Vec<i32>,
+    /// Part index of each node, `npart` in METIS manual
+    pub npart: Vec<i32>,
+    /// Edge-cut or total communication volume of the partitioning of the dual or nodal graph,
+    /// `objval` in METIS manual
+    pub objval: i32,
+}
+
+/// Partition a mesh into `nparts` parts by partitioning its dual graph
+///
+/// Two elements are connected in the dual graph if they share at least `ncommon` nodes,
+/// e.g. `ncommon = 2` for triangle meshes and `ncommon = 3` for tetrahedral meshes.
+/// Element weights of the mesh are used as vertex weights of the dual graph.
+pub fn partition_mesh_dual(mesh: &Mesh, ncommon: usize, nparts: usize) -> Result<MeshPartition> {
+    partition_mesh_dual_with_options(mesh, ncommon, nparts, None, None, &Options::default())
+}
+
+/// Partition a mesh into `nparts` parts by partitioning its dual graph with options
+///
+/// - `element_sizes` is the size of each element used for computing the total communication volume.
+/// - `tpwgts` is the desired weight of each part, and must have `nparts` elements summing to 1.
+pub fn partition_mesh_dual_with_options(
+    mesh: &Mesh,
+    ncommon: usize,
+    nparts: usize,
+    element_sizes: Option<&[i32]>,
+    tpwgts: Option<&[f32]>,
+    options: &Options,
+) -> Result<MeshPartition> {
+    let api_name = "METIS_PartMeshDual";
+    if let Some(element_sizes) = element_sizes {
+        check_len(
+            api_name,
+            "element_sizes",
+            element_sizes.len(),
+            mesh.num_elements(),
+        )?;
+    }
+    if mesh.num_weights() > 1 {
+        return Err(Error::invalid_input(
+            api_name,
+            "Mesh partitioning does not support multiple element weights",
+        ));
+    }
+    let ncommon = check_ncommon(api_name, ncommon)?;
+    part_mesh(
+        Routine::PartMeshDual,
+        mesh,
+        Some(ncommon),
+        nparts,
+        mesh.element_weights(),
+        element_sizes,
+        tpwgts,
+        options,
+    )
+}
+
+/// Partition a mesh into `nparts` parts by partitioning its nodal graph
+///
+/// Two nodes are connected in the nodal graph if they belong to the same element.
+pub fn partition_mesh_nodal(mesh: &Mesh, nparts: usize) -> Result<MeshPartition> {
+    partition_mesh_nodal_with_options(mesh, nparts, None, None, None, &Options::default())
+}
+
+/// Partition a mesh into `nparts` parts by partitioning its nodal graph with options
+///
+/// - `node_weights` and `node_sizes` are the weight and size of each node.
+///   Element weights of the mesh are not used.
+/// - `tpwgts` is the desired weight of each part, and must have `nparts` elements summing to 1.
+pub fn partition_mesh_nodal_with_options(
+    mesh: &Mesh,
+    nparts: usize,
+    node_weights: Option<&[i32]>,
+    node_sizes: Option<&[i32]>,
+    tpwgts: Option<&[f32]>,
+    options: &Options,
+) -> Result<MeshPartition> {
+    for (name, nodal) in [("node_weights", node_weights), ("node_sizes", node_sizes)] {
+        if let Some(nodal) = nodal {
+            check_len("METIS_PartMeshNodal", name, nodal.len(), mesh.num_nodes())?;
+        }
+    }
+    part_mesh(
+        Routine::PartMeshNodal,
+        mesh,
+        None,
+        nparts,
+        node_weights,
+        node_sizes,
+        tpwgts,
+        options,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn part_mesh(
+    routine: Routine,
+    mesh: &Mesh,
+    ncommon: Option<idx_t>,
+    nparts: usize,
+    vwgt: Option<&[i32]>,
+    vsize: Option<&[i32]>,
+    tpwgts: Option<&[f32]>,
+    options: &Options,
+) -> Result<MeshPartition> {
+    let api_name = match routine {
+        Routine::PartMeshDual => "METIS_PartMeshDual",
+        Routine::PartMeshNodal => "METIS_PartMeshNodal",
+        _ => unreachable!("{:?} is not a mesh partitioning routine", routine),
+    };
+    let mut options = options.to_raw(routine)?;
+    check_nparts(api_name, nparts)?;
+    for (name, weights) in [("vwgt", vwgt), ("vsize", vsize)] {
+        if let Some(weights) = weights {
+            check_non_negative(api_name, name, weights)?;
+        }
+    }
+    if let Some(tpwgts) = tpwgts {
+        check_tpwgts(api_name, tpwgts, nparts, 1)?;
+    }
+
+    // METIS does not take `const` pointers, so pass copies of the inputs
+    let mut eptr = mesh.eptr().to_vec();
+    let mut eind = mesh.eind().to_vec();
+    let mut vwgt = vwgt.map(|v| v.to_vec());
+    let mut vsize = vsize.map(|v| v.to_vec());
+    let mut tpwgts = tpwgts.map(|t| t.to_vec());
+    let mut ne = mesh.num_elements() as idx_t;
+    let mut nn = mesh.num_nodes() as idx_t;
+    let mut nparts_ = nparts as idx_t;
+
+    let mut objval = 0;
+    let mut epart = vec![0; mesh.num_elements()];
+    let mut npart = vec![0; mesh.num_nodes()];
+    match ncommon {
+        Some(mut ncommon) => {
+            unsafe {
+                METIS_PartMeshDual(
+                    &mut ne,
+                    &mut nn,
+                    eptr.as_mut_ptr(),
+                    eind.as_mut_ptr(),
+                    as_mut_ptr_or_null(&mut vwgt),
+                    as_mut_ptr_or_null(&mut vsize),
+                    &mut ncommon,
+                    &mut nparts_,
+                    as_mut_ptr_or_null(&mut tpwgts),
+                    options.as_mut_ptr(),
+                    &mut objval,
+                    epart.as_mut_ptr(),
+                    npart.as_mut_ptr(),
+                )
+            }
+            .check(api_name)?;
+        }
+        None => {
+            unsafe {
+                METIS_PartMeshNodal(
+                    &mut ne,
+                    &mut nn,
+                    eptr.as_mut_ptr(),
+                    eind.as_mut_ptr(),
+                    as_mut_ptr_or_null(&mut vwgt),
+                    as_mut_ptr_or_null(&mut vsize),
+                    &mut nparts_,
+                    as_mut_ptr_or_null(&mut tpwgts),
+                    options.as_mut_ptr(),
+                    &mut objval,
+                    epart.as_mut_ptr(),
+                    npart.as_mut_ptr(),
+                )
+            }
+            .check(api_name)?;
+        }
+    }
+
+    Ok(MeshPartition {
+        num_parts: nparts,
+        epart,
+        npart,
+        objval,
+    })
+}
+
+fn check_nparts(api_name: &str, nparts: usize) -> Result<()> {
+    if nparts == 0 {
+        return Err(Error::invalid_input(api_name, "nparts must be positive"));
+    }
+    Ok(())
+}
+
+/// Check `tpwgts` has `nparts * ncon` non-negative weights, and they sum to 1 for each constraint
+fn check_tpwgts(api_name: &str, tpwgts: &[f32], nparts: usize, ncon: usize) -> Result<()> {
+    check_len(api_name, "tpwgts", tpwgts.len(), nparts * ncon)?;
+    if let Some((i, w)) = tpwgts
+        .iter()
+        .enumerate()
+        .find(|(_, &w)| w.is_nan() || w < 0.0)
+    {
+        return Err(Error::invalid_input(
+            api_name,
+            format!("tpwgts[{}] must be non-negative, but is {}", i, w),
+        ));
+    }
+    for constraint in 0..ncon {
+        let sum: f32 = tpwgts.iter().skip(constraint).step_by(ncon).sum();
+        if (sum - 1.0).abs() > TOLERANCE {
+            return Err(Error::invalid_input(
+                api_name,
+                format!(
+                    "tpwgts of constraint {} must sum to 1, but sum to {}",
+                    constraint, sum
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check `ubvec` has `ncon` finite tolerances larger than 1
+fn check_ubvec(api_name: &str, ubvec: &[f32], ncon: usize) -> Result<()> {
+    check_len(api_name, "ubvec", ubvec.len(), ncon)?;
+    if let Some((i, u)) = ubvec
+        .iter()
+        .enumerate()
+        .find(|(_, &u)| !(u.is_finite() && u > 1.0))
+    {
+        return Err(Error::invalid_input(
+            api_name,
+            format!(
+                "ubvec[{}] must be a finite value larger than 1, but is {}",
+                i, u
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Pointer to the optional array, or `NULL` to let METIS use its default
+pub(crate) fn as_mut_ptr_or_null<T>(a: &mut Option<Vec<T>>) -> *mut T {
+    match a {
+        Some(a) => a.as_mut_ptr(),
+        None => null_mut(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{
+        graph::*,
+        mesh::{examples as mesh_examples, FromMetisMeshFormat},
+        partition::TargetPartitionWeights,
+    };
+
+    #[test]
+    fn kway_grid() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let partition = partition_kway(&graph, 4).unwrap();
+        assert_eq!(partition.num_parts, 4);
+        assert_eq!(partition.part.len(), 15);
+        assert!(partition.part.iter().all(|p| (0..4).contains(p)));
+        assert!(partition.objval > 0);
+    }
+
+    #[test]
+    fn recursive_grid() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let partition = partition_recursive(&graph, 3, None, None).unwrap();
+        assert_eq!(partition.part.len(), 15);
+        assert!(partition.part.iter().all(|p| (0..3).contains(p)));
+        assert!(partition.objval > 0);
+    }
+
+    #[test]
+    fn recursive_grid_weighted() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let tpwgts = [0.2, 0.8];
+        let ubvec = [1.05];
+        let partition = partition_recursive(&graph, 2, Some(&tpwgts), Some(&ubvec)).unwrap();
+        let num_part0 = partition.part.iter().filter(|&&p| p == 0).count();
+        assert!(num_part0 < partition.part.len() / 2);
+    }
+
+    #[test]
+    fn kway_grid_with_options() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let options = Options::default()
+            .objtype(ObjectiveType::CommunicationVolume)
+            .contig(true)
+            .seed(0);
+        let partition = partition_kway_with_options(&graph, 3, None, None, &options).unwrap();
+        assert!(partition.part.iter().all(|p| (0..3).contains(p)));
+
+        // Recursive bisection cannot minimize communication volume
+        assert!(partition_recursive_with_options(&graph, 3, None, None, &options).is_err());
+    }
+
+    #[test]
+    fn kway_grid_fortran() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let fortran = graph.clone().with_numbering(Numbering::Fortran);
+        let options = Options::default().seed(0);
+        let partition = partition_kway_with_options(&graph, 3, None, None, &options).unwrap();
+        let partition_fortran =
+            partition_kway_with_options(&fortran, 3, None, None, &options).unwrap();
+        // Part index is always 0-based
+        assert_eq!(partition, partition_fortran);
+    }
+
+    #[test]
+    fn kway_grid_weighted() {
+        // Two constraints: left half is heavy in the first, and right half in the second
+        let vwgt = (0..15)
+            .flat_map(|i| if i % 5 < 2 { [4, 1] } else { [1, 4] })
+            .collect();
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A)
+            .unwrap()
+            .with_vertex_weights(2, vwgt)
+            .unwrap()
+            .with_edge_weights(vec![2; 44])
+            .unwrap();
+        let partition =
+            partition_kway_with_options(&graph, 2, None, Some(&[1.5, 1.5]), &Options::default())
+                .unwrap();
+        assert!(partition.part.iter().all(|p| (0..2).contains(p)));
+        // Edge weights are doubled
+        assert_eq!(partition.objval % 2, 0);
+    }
+
+    #[test]
+    fn kway_grid_tpwgts() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let tpwgts = TargetPartitionWeights::from_tpwgts_str("0 = 0.2", 3, 1).unwrap();
+        let partition = partition_kway_with_options(
+            &graph,
+            3,
+            Some(tpwgts.tpwgts()),
+            None,
+            &Options::default(),
+        )
+        .unwrap();
+        assert!(partition.part.iter().all(|p| (0..3).contains(p)));
+    }
+
+    #[test]
+    fn invalid_input() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let err = partition_recursive(&graph, 2, Some(&[0.2, 0.3]), None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid input for METIS routine (METIS_PartGraphRecursive): tpwgts of constraint 0 must sum to 1, but sum to 0.5"
+        );
+        let err = partition_recursive(&graph, 2, Some(&[1.0]), None).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }));
+        let err = partition_recursive(&graph, 2, None, Some(&[1.05, 1.05])).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }));
+        let err = partition_recursive(&graph, 2, None, Some(&[1.0])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid input for METIS routine (METIS_PartGraphRecursive): ubvec[0] must be a finite value larger than 1, but is 1"
+        );
+        for u in [f32::NAN, f32::INFINITY, 0.5] {
+            let err = partition_kway_with_options(&graph, 2, None, Some(&[u]), &Options::default())
+                .unwrap_err();
+            assert!(matches!(err, Error::InvalidInput { .. }));
+        }
+        let err = partition_kway(&graph, 0).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }));
+
+        // 0 -> 1 without 1 -> 0
+        let graph = CSRGraph::from_raw_parts(vec![0, 1, 1], vec![1]);
+        let err = partition_kway(&graph, 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid input for METIS routine (METIS_PartGraphKway): Edge (0, 1) exists, but (1, 0) does not"
+        );
+
+        let mesh = Mesh::from_metis_mesh_str(mesh_examples::TRIANGLES).unwrap();
+        for ncommon in [0, idx_t::MAX as usize + 1] {
+            let err = partition_mesh_dual(&mesh, ncommon, 2).unwrap_err();
+            assert!(matches!(err, Error::InvalidInput { .. }));
+        }
+        let err = partition_mesh_nodal_with_options(
+            &mesh,
+            2,
+            Some(&[1; 4]),
+            None,
+            None,
+            &Options::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid input for METIS routine (METIS_PartMeshNodal): node_weights must have 5 elements, but has 4"
+        );
+    }
+
+    #[test]
+    fn mesh_dual() {
+        let mesh = Mesh::from_metis_mesh_str(mesh_examples::TRIANGLES).unwrap();
+        let partition = partition_mesh_dual(&mesh, 2, 2).unwrap();
+        assert_eq!(partition.epart.len(), 4);
+        assert_eq!(partition.npart.len(), 5);
+        assert!(partition.epart.iter().all(|p| (0..2).contains(p)));
+        assert!(partition.npart.iter().all(|p| (0..2).contains(p)));
+    }
+
+    #[test]
+    fn mesh_nodal() {
+        let mesh = Mesh::from_metis_mesh_str(mesh_examples::TRIANGLES).unwrap();
+        let options = Options::default().ptype(PartitioningType::RecursiveBisection);
+        let partition =
+            partition_mesh_nodal_with_options(&mesh, 2, None, None, Some(&[0.4, 0.6]), &options)
+                .unwrap();
+        assert_eq!(partition.epart.len(), 4);
+        assert_eq!(partition.npart.len(), 5);
+        assert!(partition.npart.iter().all(|p| (0..2).contains(p)));
+    }
+
+    #[test]
+    fn mesh_dual_file() {
+        let mesh = Mesh::from_metis_mesh(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../metis-src/metis-5.1.0/graphs/metis.mesh"
+        ))
+        .unwrap();
+        let partition = partition_mesh_dual(&mesh, 2, 4).unwrap();
+        assert!(partition.epart.iter().all(|p| (0..4).contains(p)));
+        assert!(partition.objval > 0);
+    }
+}
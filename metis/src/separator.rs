@@ -0,0 +1,264 @@
+//! Vertex separators
+
+use metis_sys::*;
+
+use crate::{
+    error::*, graph::CSRGraph, options::*, ordering::single_vertex_weights,
+    partition::as_mut_ptr_or_null,
+};
+
+/// Label of a vertex in two-way vertex separation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeparatorLabel {
+    Part0,
+    Part1,
+    Separator,
+}
+
+impl SeparatorLabel {
+    /// Label returned by METIS routine `api_name`, and an unexpected value is reported as [Error::InvalidOutput]
+    fn from_raw(raw: idx_t, api_name: &str) -> Result<Self> {
+        match raw {
+            0 => Ok(SeparatorLabel::Part0),
+            1 => Ok(SeparatorLabel::Part1),
+            2 => Ok(SeparatorLabel::Separator),
+            _ => Err(Error::InvalidOutput {
+                api_name: api_name.into(),
+                reason: format!("vertex label must be 0, 1 or 2, but is {}", raw),
+            }),
+        }
+    }
+
+    fn to_raw(self) -> idx_t {
+        match self {
+            SeparatorLabel::Part0 => 0,
+            SeparatorLabel::Part1 => 1,
+            SeparatorLabel::Separator => 2,
+        }
+    }
+}
+
+/// Two-way vertex separator
+///
+/// Removing the separator vertices splits the graph into two parts which have no edge between them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexSeparator {
+    /// Label of each vertex, `part` in METIS manual
+    pub labels: Vec<SeparatorLabel>,
+    /// Total weight of separator vertices, `sepsize` in METIS manual
+    pub size: usize,
+}
+
+/// Compute a two-way vertex separator
+///
+/// Vertex weights of `graph` are used if exist, and must be a single weight for each vertex.
+pub fn compute_vertex_separator(graph: &CSRGraph) -> Result<VertexSeparator> {
+    compute_vertex_separator_with_options(graph, &Options::default())
+}
+
+/// Compute a two-way vertex separator with options
+pub fn compute_vertex_separator_with_options(
+    graph: &CSRGraph,
+    options: &Options,
+) -> Result<VertexSeparator> {
+    let mut options = options.to_raw(Routine::ComputeVertexSeparator)?;
+
+    // METIS does not take `const` pointers, so pass copies of the inputs.
+    // This routine does not support `METIS_OPTION_NUMBERING`.
+    let (mut xadj, mut adjncy) = graph.to_raw_arrays(Numbering::C);
+    let mut vwgt = single_vertex_weights(graph, "METIS_ComputeVertexSeparator")?;
+    let mut nvtxs = graph.num_vertices() as idx_t;
+
+    let mut sepsize = 0;
+    let mut part = vec![0; graph.num_vertices()];
+    unsafe {
+        METIS_ComputeVertexSeparator(
+            &mut nvtxs,
+            xadj.as_mut_ptr(),
+            adjncy.as_mut_ptr(),
+            as_mut_ptr_or_null(&mut vwgt),
+            options.as_mut_ptr(),
+            &mut sepsize,
+            part.as_mut_ptr(),
+        )
+    }
+    .check("METIS_ComputeVertexSeparator")?;
+
+    Ok(VertexSeparator {
+        labels: part
+            .into_iter()
+            .map(|raw| SeparatorLabel::from_raw(raw, "METIS_ComputeVertexSeparator"))
+            .collect::<Result<_>>()?,
+        size: sepsize as usize,
+    })
+}
+
+/// Refine a user-supplied two-way vertex separator in place using one-sided FM refinement
+///
+/// `labels` must be a valid separator, i.e. no edge connects part 0 and part 1,
+/// and `ubfactor` is the allowed load imbalance between two parts, e.g. `1.05`.
+/// Returns the total weight of separator vertices after refinement
+/// as [VertexSeparator::size] of [compute_vertex_separator].
+pub fn refine_vertex_separator(
+    graph: &CSRGraph,
+    labels: &mut [SeparatorLabel],
+    ubfactor: f32,
+) -> Result<usize> {
+    let api_name = "METIS_NodeRefine";
+    check_len(api_name, "labels", labels.len(), graph.num_vertices())?;
+    if !(ubfactor.is_finite() && ubfactor > 1.0) {
+        return Err(Error::invalid_input(
+            api_name,
+            format!(
+                "ubfactor must be a finite value larger than 1, but is {}",
+                ubfactor
+            ),
+        ));
+    }
+
+    // METIS does not take `const` pointers, so pass copies of the inputs.
+    // This routine does not support `METIS_OPTION_NUMBERING`.
+    let (mut xadj, mut adjncy) = graph.to_raw_arrays(Numbering::C);
+
+    let mut vwgt =
+        single_vertex_weights(graph, api_name)?.unwrap_or_else(|| vec![1; graph.num_vertices()]);
+    for v in 0..graph.num_vertices() {
+        for &w in &adjncy[xadj[v] as usize..xadj[v + 1] as usize] {
+            if let (SeparatorLabel::Part0, SeparatorLabel::Part1) = (labels[v], labels[w as usize])
+            {
+                return Err(Error::invalid_input(
+                    api_name,
+                    format!(
+                        "labels is not a separator since edge ({}, {}) connects part 0 and part 1",
+                        v, w
+                    ),
+                ));
+            }
+        }
+    }
+    let mut where_: Vec<idx_t> = labels.iter().map(|label| label.to_raw()).collect();
+    // -1 allows the vertex to move to either part
+    let mut hmarker = vec![-1; graph.num_vertices()];
+    unsafe {
+        METIS_NodeRefine(
+            graph.num_vertices() as idx_t,
+            xadj.as_mut_ptr(),
+            vwgt.as_mut_ptr(),
+            adjncy.as_mut_ptr(),
+            where_.as_mut_ptr(),
+            hmarker.as_mut_ptr(),
+            ubfactor,
+        )
+    }
+    .check(api_name)?;
+
+    // Keep `labels` untouched if METIS returns an invalid label
+    let refined = where_
+        .into_iter()
+        .map(|raw| SeparatorLabel::from_raw(raw, api_name))
+        .collect::<Result<Vec<_>>>()?;
+    labels.copy_from_slice(&refined);
+    let size = labels
+        .iter()
+        .zip(&vwgt)
+        .filter(|(&label, _)| label == SeparatorLabel::Separator)
+        .map(|(_, &w)| w as usize)
+        .sum();
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::graph::*;
+
+    /// Check that no edge connects part 0 and part 1
+    fn assert_separated(graph: &CSRGraph, labels: &[SeparatorLabel]) {
+        for v in 0..graph.num_vertices() {
+            let range = graph.xadj()[v] as usize..graph.xadj()[v + 1] as usize;
+            for &w in &graph.adjncy()[range] {
+                let pair = (labels[v], labels[w as usize]);
+                assert_ne!(pair, (SeparatorLabel::Part0, SeparatorLabel::Part1));
+                assert_ne!(pair, (SeparatorLabel::Part1, SeparatorLabel::Part0));
+            }
+        }
+    }
+
+    #[test]
+    fn label_from_raw() {
+        assert_eq!(
+            SeparatorLabel::from_raw(2, "METIS_Test").unwrap(),
+            SeparatorLabel::Separator
+        );
+        assert!(matches!(
+            SeparatorLabel::from_raw(3, "METIS_Test").unwrap_err(),
+            Error::InvalidOutput { .. }
+        ));
+    }
+
+    #[test]
+    fn compute_grid() {
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        let sep = compute_vertex_separator(&graph).unwrap();
+        assert_eq!(sep.labels.len(), 15);
+        let num_sep = sep
+            .labels
+            .iter()
+            .filter(|&&label| label == SeparatorLabel::Separator)
+            .count();
+        assert_eq!(sep.size, num_sep);
+        assert_separated(&graph, &sep.labels);
+    }
+
+    #[test]
+    fn refine_grid() {
+        use SeparatorLabel::*;
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A).unwrap();
+        // 5x3 grid separated by the 3rd column, which is already minimal
+        #[rustfmt::skip]
+        let mut labels = vec![
+            Part0, Part0, Separator, Part1, Part1,
+            Part0, Part0, Separator, Part1, Part1,
+            Part0, Part0, Separator, Part1, Part1,
+        ];
+        let size = refine_vertex_separator(&graph, &mut labels, 1.5).unwrap();
+        assert!(size <= 3);
+        assert_separated(&graph, &labels);
+
+        let mut labels = vec![SeparatorLabel::Part0; 14];
+        let err = refine_vertex_separator(&graph, &mut labels, 1.5).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }));
+    }
+
+    #[test]
+    fn refine_weighted() {
+        use SeparatorLabel::*;
+        let graph = CSRGraph::from_metis_graph_str(examples::MANUAL_3A)
+            .unwrap()
+            .with_vertex_weights(1, vec![2; 15])
+            .unwrap();
+        #[rustfmt::skip]
+        let separated = vec![
+            Part0, Part0, Separator, Part1, Part1,
+            Part0, Part0, Separator, Part1, Part1,
+            Part0, Part0, Separator, Part1, Part1,
+        ];
+        let mut labels = separated.clone();
+        let size = refine_vertex_separator(&graph, &mut labels, 1.5).unwrap();
+        let num_sep = labels.iter().filter(|&&label| label == Separator).count();
+        assert_eq!(size, 2 * num_sep);
+
+        for ubfactor in [1.0, f32::NAN, f32::INFINITY] {
+            let mut labels = separated.clone();
+            let err = refine_vertex_separator(&graph, &mut labels, ubfactor).unwrap_err();
+            assert!(matches!(err, Error::InvalidInput { .. }));
+        }
+
+        // Vertices 1 and 2 in different parts are adjacent
+        let mut labels = separated.clone();
+        labels[2] = Part1;
+        let err = refine_vertex_separator(&graph, &mut labels, 1.5).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }));
+        assert_eq!(labels[2], Part1);
+    }
+}